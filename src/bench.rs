@@ -0,0 +1,214 @@
+//! Compare the CPU parallelization strategies on a few representative views, and the
+//! CPU with the GPU at a few resolutions and iteration limits, run with `mandelbrot bench`.
+use std::time::{Duration, Instant};
+
+use num::Complex;
+use winit::dpi::PhysicalSize;
+
+use mandelbrot::cpu::{self, EscapeCounts, Iteration, Parallelism};
+use mandelbrot::export::{Camera, Style};
+use mandelbrot::gpu::{AdapterChoice, Offscreen};
+use mandelbrot::{RenderParams, YAxis};
+
+const RESOLUTION: (usize, usize) = (1024, 768);
+const RUNS: u32 = 5;
+
+const RENDERER_RESOLUTIONS: [(usize, usize); 3] = [(640, 480), (1920, 1080), (3840, 2160)];
+const RENDERER_ITERATIONS: [u32; 2] = [256, 2048];
+const RENDERER_RUNS: u32 = 3;
+
+const RENDERER_VIEWS: [View; 3] = [
+    View {
+        name: "full set",
+        center_point: mandelbrot::DEFAULT_CENTER_POINT,
+        zoom: mandelbrot::DEFAULT_ZOOM,
+    },
+    View {
+        name: "boundary",
+        center_point: (-0.745, 0.11),
+        zoom: 50.0,
+    },
+    // As deep as the f32 of the GPU still renders the same image as the CPU
+    View {
+        name: "deep",
+        center_point: (-0.743643887037151, 0.13182590420533),
+        zoom: 1.0e4,
+    },
+];
+
+struct View {
+    name: &'static str,
+    center_point: (f64, f64),
+    zoom: f64,
+}
+
+const VIEWS: [View; 3] = [
+    // Almost the whole screen is inside the main cardioid, every pixel runs to the limit.
+    View {
+        name: "interior",
+        center_point: (-0.2, 0.0),
+        zoom: 4.0,
+    },
+    // Above the set, most pixels escape within a few iterations.
+    View {
+        name: "exterior",
+        center_point: (0.8, 1.0),
+        zoom: 2.0,
+    },
+    View {
+        name: "mixed",
+        center_point: mandelbrot::DEFAULT_CENTER_POINT,
+        zoom: mandelbrot::DEFAULT_ZOOM,
+    },
+];
+
+/// Run the benchmarks, with the CPU on `threads` threads, 0 for all of them, and on the
+/// GPU of `adapter`.
+pub fn run(threads: usize, adapter: &AdapterChoice) {
+    compare_parallelism(threads);
+    println!();
+    compare_renderers(threads, adapter);
+}
+
+/// Time the CPU parallelization strategies against each other.
+fn compare_parallelism(threads: usize) {
+    let threads = cpu::thread_count(threads);
+    println!(
+        "Average of {} runs at {}x{} on {} threads",
+        RUNS, RESOLUTION.0, RESOLUTION.1, threads
+    );
+
+    print!("{:<10}", "view");
+    for parallelism in Parallelism::ALL {
+        print!("{:>12}", format!("{:?}", parallelism));
+    }
+    println!();
+
+    let mut counts = EscapeCounts::default();
+    counts.resize(RESOLUTION);
+    for view in VIEWS {
+        let (top_left, (width, height)) = mandelbrot::center_to_start_conditions(
+            view.center_point,
+            view.zoom,
+            PhysicalSize::new(RESOLUTION.0 as u32, RESOLUTION.1 as u32),
+            YAxis::Up,
+        );
+        let upper_left = Complex::new(top_left.0, top_left.1);
+        let lower_right = Complex::new(top_left.0 + width, top_left.1 - height);
+
+        print!("{:<10}", view.name);
+        for parallelism in Parallelism::ALL {
+            let start = Instant::now();
+            for _ in 0..RUNS {
+                cpu::render_parallel(
+                    &mut counts,
+                    upper_left,
+                    lower_right,
+                    Iteration::default(),
+                    parallelism,
+                    threads,
+                );
+            }
+            let average = start.elapsed() / RUNS;
+            print!(
+                "{:>12}",
+                format!("{:.2} ms", average.as_secs_f64() * 1000.0)
+            );
+        }
+        println!();
+    }
+}
+
+/// Time the CPU and the GPU rendering the same views into RGB pixels, the GPU's time
+/// split into the render pass and reading the image back.
+fn compare_renderers(threads: usize, adapter: &AdapterChoice) {
+    let gpu = match pollster::block_on(Offscreen::new(adapter)) {
+        Ok(gpu) => Some(gpu),
+        Err(error) => {
+            println!("Benchmarking only the CPU: {}", error);
+            None
+        }
+    };
+    println!(
+        "Average of {} runs, the CPU on {} threads",
+        RENDERER_RUNS,
+        cpu::thread_count(threads)
+    );
+    println!(
+        "{:<10}{:>11}{:>7}{:>12}{:>12}{:>12}{:>12}",
+        "view", "resolution", "iter", "CPU", "GPU", "GPU render", "readback"
+    );
+
+    let style = Style::default();
+    let milliseconds = |time: Duration| format!("{:.2} ms", time.as_secs_f64() * 1000.0);
+    for view in RENDERER_VIEWS {
+        for resolution in RENDERER_RESOLUTIONS {
+            for limit in RENDERER_ITERATIONS {
+                let params = RenderParams {
+                    camera: Camera {
+                        center: view.center_point,
+                        zoom: view.zoom,
+                    },
+                    resolution,
+                    iteration: Iteration {
+                        limit,
+                        ..Iteration::default()
+                    },
+                    style: style.clone(),
+                    threads,
+                };
+                let start = Instant::now();
+                for _ in 0..RENDERER_RUNS {
+                    mandelbrot::render(&params);
+                }
+                let cpu_time = start.elapsed() / RENDERER_RUNS;
+                print!(
+                    "{:<10}{:>11}{:>7}{:>12}",
+                    view.name,
+                    format!("{}x{}", resolution.0, resolution.1),
+                    limit,
+                    milliseconds(cpu_time)
+                );
+
+                let Some(gpu) = gpu.as_ref() else {
+                    println!();
+                    continue;
+                };
+                let render = || {
+                    gpu.render_timed(
+                        params.camera,
+                        PhysicalSize::new(resolution.0 as u32, resolution.1 as u32),
+                        params.iteration,
+                        &style,
+                    )
+                };
+                // A first render warms the GPU up, which isn't measured
+                let measured = render().and_then(|_| {
+                    let start = Instant::now();
+                    let times = (0..RENDERER_RUNS)
+                        .map(|_| render().map(|(_, times)| times))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok((start.elapsed() / RENDERER_RUNS, times))
+                });
+                match measured {
+                    Ok((total, times)) => {
+                        let render_time = times
+                            .iter()
+                            .map(|times| times.render)
+                            .sum::<Option<Duration>>()
+                            .map(|sum| sum / RENDERER_RUNS);
+                        let readback = times.iter().map(|times| times.readback).sum::<Duration>()
+                            / RENDERER_RUNS;
+                        println!(
+                            "{:>12}{:>12}{:>12}",
+                            milliseconds(total),
+                            render_time.map_or("-".to_string(), milliseconds),
+                            milliseconds(readback)
+                        );
+                    }
+                    Err(error) => println!("{:>12}", format!("failed: {}", error)),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Views saved under the numbered slots 1 to 9, kept in a TOML file next to the binary.
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use num::Complex;
+
+use crate::cpu::{
+    EscapeNorm, FractalKind, InitialZ, Iteration, DEFAULT_BAILOUT_SQUARED, DEFAULT_ITERATION_LIMIT,
+    DEFAULT_POWER,
+};
+use crate::error::Error;
+
+/// A view along with the iteration settings it was saved with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+// Settings added since are missing from older files, and take their defaults
+#[serde(default)]
+pub struct Bookmark {
+    pub center_point: (f64, f64),
+    pub zoom: f64,
+    pub fractal_kind: FractalKind,
+    pub escape_norm: EscapeNorm,
+    pub initial_z: InitialZ,
+    pub max_iterations: u32,
+    pub adaptive_iterations: bool,
+    pub bailout_squared: f64,
+    pub julia_c: Option<(f32, f32)>,
+    pub power: u32,
+}
+
+impl Default for Bookmark {
+    /// The view the application starts with.
+    fn default() -> Self {
+        Bookmark {
+            center_point: crate::DEFAULT_CENTER_POINT,
+            zoom: crate::DEFAULT_ZOOM,
+            fractal_kind: FractalKind::default(),
+            escape_norm: EscapeNorm::default(),
+            initial_z: InitialZ::default(),
+            max_iterations: DEFAULT_ITERATION_LIMIT,
+            adaptive_iterations: false,
+            bailout_squared: DEFAULT_BAILOUT_SQUARED,
+            julia_c: None,
+            power: DEFAULT_POWER,
+        }
+    }
+}
+
+impl Bookmark {
+    /// The iteration settings of the view, for rendering it on the CPU.
+    pub fn iteration(&self) -> Iteration {
+        Iteration {
+            kind: self.fractal_kind,
+            limit: crate::iteration_limit(self.max_iterations, self.adaptive_iterations, self.zoom),
+            norm: self.escape_norm,
+            initial_z: self.initial_z,
+            bailout_squared: self.bailout_squared,
+            julia_c: self
+                .julia_c
+                .map(|(re, im)| Complex::new(re as f64, im as f64)),
+            power: self.power,
+            trap: None,
+            fill_interior: false,
+            interior_periods: false,
+            // Only faster, the counts are the same
+            periodicity_checking: true,
+        }
+    }
+}
+
+/// The saved bookmarks, by slot.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Bookmarks {
+    // TOML tables only have string keys, so the slots are kept as such
+    slots: BTreeMap<String, Bookmark>,
+}
+
+impl Bookmarks {
+    /// The file the bookmarks are kept in, next to the binary.
+    pub fn path() -> Result<PathBuf, Error> {
+        Ok(std::env::current_exe()?.with_file_name("bookmarks.toml"))
+    }
+
+    /// Load the bookmarks from `path`, none if the file doesn't exist yet.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Ok(toml::from_str(&text)?),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Bookmarks::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, slot: u8) -> Option<&Bookmark> {
+        self.slots.get(&slot.to_string())
+    }
+
+    pub fn set(&mut self, slot: u8, bookmark: Bookmark) {
+        self.slots.insert(slot.to_string(), bookmark);
+    }
+}
@@ -0,0 +1,230 @@
+//! The startup settings, read from a `mandelbrot.toml` in the working directory or in
+//! the platform's configuration directory.
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use mandelbrot::cpu::{ColorMapping, FractalKind, DEFAULT_ITERATION_LIMIT};
+use mandelbrot::error::Error;
+use mandelbrot::gpu::{AdapterChoice, PowerPreference};
+use mandelbrot::YAxis;
+
+const FILE_NAME: &str = "mandelbrot.toml";
+
+/// What renders the fractal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// The GPU, falling back to the CPU when there is none.
+    #[default]
+    Gpu,
+    Cpu,
+}
+
+impl Backend {
+    /// The name shown in the window title and the state dump.
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Gpu => "GPU",
+            Backend::Cpu => "CPU",
+        }
+    }
+}
+
+/// The settings the application starts with, every one of them optional in the file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The inner size of the window in logical pixels.
+    pub width: f64,
+    pub height: f64,
+    pub backend: Backend,
+    /// Which kind of GPU to render on when there are several, "default", "low-power" or
+    /// "high-performance".
+    pub power_preference: PowerPreference,
+    /// The GPU to render on, by its index in the list printed when none matches or by a
+    /// part of its name, overriding the power preference.
+    pub adapter: Option<String>,
+    pub max_iterations: u32,
+    /// A GIMP palette file to color with.
+    pub palette: Option<PathBuf>,
+    pub center_point: (f64, f64),
+    pub zoom: f64,
+    /// Print the size and time of every frame, the time the GPU took where it can tell, and
+    /// the mouse input.
+    pub verbose: bool,
+    /// Show what renders the view, its zoom and the frame rate in the window title,
+    /// instead of only the name.
+    pub live_title: bool,
+    /// The frame rate animations redraw at, at most, 0 for as fast as the renderer goes.
+    pub max_fps: f64,
+    /// Stop iterating the orbits of the CPU renders caught in a cycle early, which only
+    /// makes them faster, turned off to compare with the orbits run to the limit.
+    pub periodicity_checking: bool,
+    /// The number of threads the CPU renders on, 0 for all of them.
+    pub threads: usize,
+    /// The gamma the colors are looked up with, below 1.0 brightening the filaments.
+    pub gamma: f32,
+    /// Dither the colors with a Bayer matrix, against banding in gentle gradients.
+    pub dither: bool,
+    /// Which way the imaginary axis points, "up" or "down".
+    pub y_axis: YAxis,
+    /// How much scrolling by one line adds to the zoom.
+    pub zoom_per_line: f64,
+    /// How many logical pixels of a scroll by pixels, as touchpads do, count as one line,
+    /// so a swipe zooms about as far as a notch of a mouse wheel whatever the screen.
+    pub pixels_per_line: f64,
+    /// The zoom scrolling and box zooming stay within, the minimum being exclusive so
+    /// the default of 0.0 only keeps the view from flipping upside down.
+    pub min_zoom: f64,
+    pub max_zoom: f64,
+    /// The settings of single fractal kinds, in `[kinds.BurningShip]` tables for example,
+    /// taking the place of the ones above while the kind is shown.
+    // Last, as TOML tables can't be followed by plain values
+    pub kinds: BTreeMap<FractalKind, KindConfig>,
+}
+
+/// The settings of one fractal kind, those left out being the ones of the whole config.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KindConfig {
+    pub max_iterations: Option<u32>,
+    pub palette: Option<PathBuf>,
+    /// How the counts are spread over the palette, "Linear", "SquareRoot" or
+    /// "Logarithmic".
+    pub color_mapping: Option<ColorMapping>,
+    /// The view the kind is framed with at the default power, rather than the whole of it.
+    pub center_point: Option<(f64, f64)>,
+    pub zoom: Option<f64>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            width: 1024.0,
+            height: 768.0,
+            backend: Backend::default(),
+            power_preference: PowerPreference::default(),
+            adapter: None,
+            max_iterations: DEFAULT_ITERATION_LIMIT,
+            palette: None,
+            center_point: mandelbrot::DEFAULT_CENTER_POINT,
+            zoom: mandelbrot::DEFAULT_ZOOM,
+            verbose: false,
+            live_title: true,
+            max_fps: 60.0,
+            periodicity_checking: true,
+            threads: 0,
+            gamma: 1.0,
+            dither: false,
+            y_axis: YAxis::default(),
+            zoom_per_line: 0.1,
+            pixels_per_line: 40.0,
+            min_zoom: 0.0,
+            max_zoom: f64::INFINITY,
+            kinds: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the first config file found, the defaults if there is none.
+    pub fn load() -> Result<Self, Error> {
+        let candidates = [
+            Some(PathBuf::from(FILE_NAME)),
+            config_dir().map(|dir| dir.join(FILE_NAME)),
+        ];
+        for path in candidates.into_iter().flatten() {
+            match std::fs::read_to_string(&path) {
+                Ok(text) => {
+                    println!("Using the settings in {}", path.display());
+                    let config: Config = toml::from_str(&text)?;
+                    config.validate()?;
+                    return Ok(config);
+                }
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(error) => return Err(error.into()),
+            }
+        }
+        Ok(Config::default())
+    }
+
+    /// Fail on the settings the application can't start with, which the file's types
+    /// alone don't rule out.
+    fn validate(&self) -> Result<(), Error> {
+        let invalid = |reason: String| Err(Error::Config(reason));
+        let limits = crate::ITERATION_LIMITS;
+        if !limits.contains(&self.max_iterations) {
+            return invalid(format!(
+                "max_iterations must be between {} and {}",
+                limits.start(),
+                limits.end()
+            ));
+        }
+        let limits = crate::GAMMA_LIMITS;
+        if !limits.contains(&self.gamma) {
+            return invalid(format!(
+                "gamma must be between {} and {}",
+                limits.start(),
+                limits.end()
+            ));
+        }
+        if !(self.pixels_per_line.is_finite() && self.pixels_per_line > 0.0) {
+            return invalid("pixels_per_line must be positive".into());
+        }
+        if !(self.zoom_per_line.is_finite() && self.zoom_per_line > 0.0) {
+            return invalid("zoom_per_line must be positive".into());
+        }
+        if !(self.max_fps.is_finite() && self.max_fps >= 0.0) {
+            return invalid("max_fps can't be negative".into());
+        }
+        for (kind, settings) in &self.kinds {
+            let limits = crate::ITERATION_LIMITS;
+            if settings
+                .max_iterations
+                .is_some_and(|iterations| !limits.contains(&iterations))
+            {
+                return invalid(format!(
+                    "max_iterations of {:?} must be between {} and {}",
+                    kind,
+                    limits.start(),
+                    limits.end()
+                ));
+            }
+            if let Some((re, im)) = settings.center_point {
+                if !(re.is_finite() && im.is_finite()) {
+                    return invalid(format!("center_point of {:?} must be finite", kind));
+                }
+            }
+            if let Some(zoom) = settings.zoom {
+                if !(zoom.is_finite() && zoom > 0.0) {
+                    return invalid(format!("zoom of {:?} must be positive", kind));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The GPU asked for.
+    pub fn adapter_choice(&self) -> AdapterChoice {
+        AdapterChoice {
+            power_preference: self.power_preference,
+            adapter: self.adapter.clone(),
+        }
+    }
+}
+
+/// The directory of the user's configuration files on the current platform.
+fn config_dir() -> Option<PathBuf> {
+    let from_env = |name| std::env::var_os(name).map(PathBuf::from);
+    if cfg!(windows) {
+        from_env("APPDATA").map(|dir| dir.join("mandelbrot"))
+    } else if cfg!(target_os = "macos") {
+        from_env("HOME").map(|dir| dir.join("Library/Application Support/mandelbrot"))
+    } else {
+        from_env("XDG_CONFIG_HOME")
+            .or_else(|| from_env("HOME").map(|dir| dir.join(".config")))
+            .map(|dir| dir.join("mandelbrot"))
+    }
+}
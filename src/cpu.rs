@@ -1,54 +1,1003 @@
 use image::codecs::png::PngEncoder;
 use image::ImageEncoder;
 use num::Complex;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs::File;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use wide::{f64x4, CmpGe, CmpLt};
 
-fn escape_time(c: Complex<f64>, limit: usize) -> Option<usize> {
-    let mut z = Complex::<f64>::default();
+use crate::export::Style;
+use crate::palette;
 
-    for i in 0..limit {
-        if z.norm_sqr() >= 4.0 {
-            return Some(i);
+/// The norm used to decide whether `z` escaped the bailout circle.
+///
+/// Other than the usual L2 norm, the L∞ and L1 norms make the escape region a square
+/// and a diamond respectively, which is visible in the shape of the bands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum EscapeNorm {
+    #[default]
+    L2,
+    LInfinity,
+    L1,
+}
+
+impl EscapeNorm {
+    pub fn next(self) -> Self {
+        match self {
+            EscapeNorm::L2 => EscapeNorm::LInfinity,
+            EscapeNorm::LInfinity => EscapeNorm::L1,
+            EscapeNorm::L1 => EscapeNorm::L2,
+        }
+    }
+
+    /// The value identifying the norm in the shader.
+    pub fn shader_id(self) -> u32 {
+        match self {
+            EscapeNorm::L2 => 0,
+            EscapeNorm::LInfinity => 1,
+            EscapeNorm::L1 => 2,
+        }
+    }
+
+    fn escaped(self, z: Complex<f64>, bailout_squared: f64) -> bool {
+        match self {
+            EscapeNorm::L2 => z.norm_sqr() >= bailout_squared,
+            EscapeNorm::LInfinity => z.re.abs().max(z.im.abs()) >= bailout_squared.sqrt(),
+            EscapeNorm::L1 => z.l1_norm() >= bailout_squared.sqrt(),
+        }
+    }
+}
+
+/// The value `z` starts the iteration from, as a function of the pixel's point `c`.
+///
+/// Starting anywhere but zero warps the Mandelbrot set into hybrids of it and the
+/// Julia sets of the points it starts from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum InitialZ {
+    #[default]
+    Zero,
+    /// Half of the pixel's point.
+    HalfPoint,
+    /// A small sine ripple running across the plane.
+    Ripple,
+}
+
+impl InitialZ {
+    pub fn next(self) -> Self {
+        match self {
+            InitialZ::Zero => InitialZ::HalfPoint,
+            InitialZ::HalfPoint => InitialZ::Ripple,
+            InitialZ::Ripple => InitialZ::Zero,
+        }
+    }
+
+    /// The value identifying the initial z in the shader.
+    pub fn shader_id(self) -> u32 {
+        match self {
+            InitialZ::Zero => 0,
+            InitialZ::HalfPoint => 1,
+            InitialZ::Ripple => 2,
+        }
+    }
+
+    fn start(self, c: Complex<f64>) -> Complex<f64> {
+        match self {
+            InitialZ::Zero => Complex::default(),
+            InitialZ::HalfPoint => c / 2.0,
+            InitialZ::Ripple => Complex::new(0.2 * (4.0 * c.im).sin(), 0.2 * (4.0 * c.re).sin()),
+        }
+    }
+}
+
+/// How the escape counts are spread over the palette, before the gamma is applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColorMapping {
+    /// In proportion to the count, which suits zoomed in views with close counts.
+    #[default]
+    Linear,
+    /// The square root of the count, in between the other two.
+    SquareRoot,
+    /// The logarithm of the count, bringing out the faint detail of the low counts far
+    /// from the set.
+    Logarithmic,
+}
+
+impl ColorMapping {
+    pub fn next(self) -> Self {
+        match self {
+            ColorMapping::Linear => ColorMapping::SquareRoot,
+            ColorMapping::SquareRoot => ColorMapping::Logarithmic,
+            ColorMapping::Logarithmic => ColorMapping::Linear,
+        }
+    }
+
+    /// The value identifying the mapping in the shader.
+    pub fn shader_id(self) -> u32 {
+        match self {
+            ColorMapping::Linear => 0,
+            ColorMapping::SquareRoot => 1,
+            ColorMapping::Logarithmic => 2,
+        }
+    }
+
+    /// The position in the palette of `count` out of `limit`, from 0.0 to 1.0.
+    pub fn position(self, count: f32, limit: u32) -> f32 {
+        match self {
+            ColorMapping::Linear => count / limit as f32,
+            ColorMapping::SquareRoot => (count / limit as f32).sqrt(),
+            ColorMapping::Logarithmic => count.ln_1p() / (limit as f32).ln_1p(),
+        }
+    }
+}
+
+/// The formula iterated for every point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+pub enum FractalKind {
+    /// z = z² + c
+    #[default]
+    Mandelbrot,
+    /// z = (|re z| + i|im z|)² + c
+    BurningShip,
+}
+
+impl FractalKind {
+    pub fn next(self) -> Self {
+        match self {
+            FractalKind::Mandelbrot => FractalKind::BurningShip,
+            FractalKind::BurningShip => FractalKind::Mandelbrot,
         }
-        z = z * z + c;
     }
 
+    /// The value identifying the fractal in the shader.
+    pub fn shader_id(self) -> u32 {
+        match self {
+            FractalKind::Mandelbrot => 0,
+            FractalKind::BurningShip => 1,
+        }
+    }
+
+    /// One iteration, raising `z` to `power`, 2 being the classic fractals.
+    fn step(self, z: Complex<f64>, c: Complex<f64>, power: u32) -> Complex<f64> {
+        let z = match self {
+            FractalKind::Mandelbrot => z,
+            FractalKind::BurningShip => Complex::new(z.re.abs(), z.im.abs()),
+        };
+        if power == 2 {
+            return z * z + c;
+        }
+        // Repeated multiplication, as powi goes through polar coordinates for complex
+        // numbers, which is slower and less accurate for small powers
+        let mut raised = z;
+        for _ in 1..power {
+            raised *= z;
+        }
+        raised + c
+    }
+}
+
+/// The shape an orbit trap measures the distance of the orbit from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TrapShape {
+    #[default]
+    Point,
+    /// The horizontal line through the trap's center.
+    Line,
+    /// The circle of the trap's radius around its center.
+    Circle,
+}
+
+impl TrapShape {
+    pub const ALL: [TrapShape; 3] = [TrapShape::Point, TrapShape::Line, TrapShape::Circle];
+
+    /// The value identifying the shape in the shader, 0 meaning no trap.
+    pub fn shader_id(self) -> u32 {
+        match self {
+            TrapShape::Point => 1,
+            TrapShape::Line => 2,
+            TrapShape::Circle => 3,
+        }
+    }
+}
+
+/// Coloring points by how close their orbit gets to a shape, instead of by how quickly
+/// it escapes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrbitTrap {
+    pub shape: TrapShape,
+    pub center: Complex<f64>,
+    /// The radius of the circle, unused by the other shapes.
+    pub radius: f64,
+}
+
+impl OrbitTrap {
+    fn distance(self, z: Complex<f64>) -> f64 {
+        match self.shape {
+            TrapShape::Point => (z - self.center).norm(),
+            TrapShape::Line => (z.im - self.center.im).abs(),
+            TrapShape::Circle => ((z - self.center).norm() - self.radius).abs(),
+        }
+    }
+}
+
+pub const DEFAULT_ITERATION_LIMIT: u32 = 255;
+/// The power `z` is raised to by the classic fractals, higher ones making Multibrots.
+pub const DEFAULT_POWER: u32 = 2;
+/// The square of the smallest bailout radius, 2, beyond which every orbit diverges.
+pub const DEFAULT_BAILOUT_SQUARED: f64 = 4.0;
+/// The radius of the circle orbit trap to start with.
+pub const DEFAULT_TRAP_RADIUS: f64 = 0.5;
+
+/// The settings of the iteration run for every point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Iteration {
+    pub kind: FractalKind,
+    /// The number of iterations after which a point is considered part of the set.
+    pub limit: u32,
+    pub norm: EscapeNorm,
+    pub initial_z: InitialZ,
+    /// The square of the radius `z` has to leave to escape, at least
+    /// [`DEFAULT_BAILOUT_SQUARED`]. Larger radii make for smoother color gradients.
+    pub bailout_squared: f64,
+    /// The constant of the Julia set to render instead of the Mandelbrot set.
+    ///
+    /// In that case every point is the starting `z` and the initial z setting is ignored.
+    pub julia_c: Option<Complex<f64>>,
+    /// The power `z` is raised to in every iteration, at least 2.
+    pub power: u32,
+    /// Color by the closest distance of the orbit to the trap, rather than by the
+    /// escape count.
+    pub trap: Option<OrbitTrap>,
+    /// Fill rectangles whose whole border is inside the set without iterating their
+    /// inside, which makes views with a lot of interior much faster on the CPU.
+    ///
+    /// The set is connected, so the inside of such a rectangle is in the set as well,
+    /// except for filaments thinner than the pixels missing between the border pixels.
+    pub fill_interior: bool,
+    /// Give the points that don't escape the period of the cycle their orbit settles
+    /// into, negated to set it apart from the escape counts, so the interior is colored
+    /// by it and shows the bulbs. Points whose orbits don't settle stay without a count.
+    pub interior_periods: bool,
+    /// Stop iterating the orbits that come back to where they were a while ago, which
+    /// are caught in a cycle and never escape, instead of running them to the limit.
+    ///
+    /// Views with a lot of interior render much faster, and the points escaping are the
+    /// same as without it.
+    pub periodicity_checking: bool,
+}
+
+impl Default for Iteration {
+    fn default() -> Self {
+        Iteration {
+            kind: FractalKind::default(),
+            limit: DEFAULT_ITERATION_LIMIT,
+            norm: EscapeNorm::default(),
+            initial_z: InitialZ::default(),
+            bailout_squared: DEFAULT_BAILOUT_SQUARED,
+            julia_c: None,
+            power: DEFAULT_POWER,
+            trap: None,
+            fill_interior: false,
+            interior_periods: false,
+            periodicity_checking: false,
+        }
+    }
+}
+
+/// The number of iterations it took `point` to escape, made continuous by how far past
+/// the bailout `z` got, so the colors don't form discrete bands.
+///
+/// With an orbit trap, it's the closest distance of the orbit to the trap instead.
+/// None for the points that don't escape within the iteration limit.
+pub fn escape_time(point: Complex<f64>, iteration: Iteration) -> Option<f32> {
+    if let Some(trap) = iteration.trap {
+        return Some(trapped_distance(point, iteration, trap));
+    }
+    if let Some(period) = known_period(point, iteration) {
+        return iteration.interior_periods.then_some(-(period as f32));
+    }
+    let (mut z, c) = starting_values(point, iteration);
+    let mut cycle = CycleCheck::new(z);
+
+    for i in 0..iteration.limit {
+        if iteration.norm.escaped(z, iteration.bailout_squared) {
+            return Some(smooth_count(i, z, iteration));
+        }
+        z = iteration.kind.step(z, c, iteration.power);
+        if iteration.periodicity_checking && cycle.caught(i, z) {
+            break;
+        }
+    }
+
+    if iteration.interior_periods {
+        return orbit_period(z, c, iteration).map(|period| -(period as f32));
+    }
     None
 }
 
-fn pixel_to_point(
+// How close an orbit has to come back to the reference point of the periodicity check
+// to count as caught in a cycle, small enough not to catch the orbits that only pass
+// near it before escaping
+const PERIODICITY_TOLERANCE: f64 = 1.0e-10;
+// The iterations before the reference point is first moved along the orbit
+const FIRST_REFERENCE: u32 = 8;
+
+/// The periodicity check of an orbit, comparing every `z` with a reference point of the
+/// orbit that is moved up to the current `z` after twice as many iterations every time,
+/// so cycles of any period are caught once the reference is in them.
+struct CycleCheck {
+    reference: Complex<f64>,
+    next_reference: u32,
+}
+
+impl CycleCheck {
+    fn new(z: Complex<f64>) -> Self {
+        CycleCheck {
+            reference: z,
+            next_reference: FIRST_REFERENCE,
+        }
+    }
+
+    /// Whether `z`, reached by the iteration `i` counting from 0, is back at the
+    /// reference point.
+    fn caught(&mut self, i: u32, z: Complex<f64>) -> bool {
+        if (z - self.reference).norm_sqr() < PERIODICITY_TOLERANCE * PERIODICITY_TOLERANCE {
+            return true;
+        }
+        if i + 1 == self.next_reference {
+            self.reference = z;
+            self.next_reference = self.next_reference.saturating_mul(2);
+        }
+        false
+    }
+}
+
+// The longest cycle looked for, and how close the orbit has to come back to where it
+// was to count as having gone around it
+const MAX_PERIOD: u32 = 256;
+const PERIOD_TOLERANCE: f64 = 1.0e-9;
+
+/// The period of the cycle the orbit at `z`, iterated with the constant `c` until the
+/// limit, has settled into, found by iterating on until `z` comes back to where it
+/// started. None when it doesn't within [`MAX_PERIOD`] iterations, as near the boundary
+/// where the orbits settle too slowly.
+fn orbit_period(z: Complex<f64>, c: Complex<f64>, iteration: Iteration) -> Option<u32> {
+    let start = z;
+    let mut z = z;
+    for period in 1..=MAX_PERIOD {
+        z = iteration.kind.step(z, c, iteration.power);
+        if (z - start).norm_sqr() < PERIOD_TOLERANCE * PERIOD_TOLERANCE {
+            return Some(period);
+        }
+    }
+    None
+}
+
+/// The closest the orbit of `point` gets to `trap` before escaping, mapped from 0.0 to
+/// infinity into the count range from 0 to the iteration limit, so it's colored through
+/// the palette like the escape counts.
+///
+/// Points inside the set get a distance too, their orbits running until the limit.
+fn trapped_distance(point: Complex<f64>, iteration: Iteration, trap: OrbitTrap) -> f32 {
+    let (mut z, c) = starting_values(point, iteration);
+    // Orbits escaping right away are never iterated, and are as close as they start, so
+    // they stay in the palette instead of reaching the limit the GPU draws as interior
+    let mut closest = if iteration.norm.escaped(z, iteration.bailout_squared) {
+        trap.distance(z)
+    } else {
+        f64::MAX
+    };
+    for _ in 0..iteration.limit {
+        if iteration.norm.escaped(z, iteration.bailout_squared) {
+            break;
+        }
+        z = iteration.kind.step(z, c, iteration.power);
+        closest = closest.min(trap.distance(z));
+    }
+    (closest / (1.0 + closest) * iteration.limit as f64) as f32
+}
+
+/// The initial `z` and the constant `c` iterating `point` starts from.
+fn starting_values(point: Complex<f64>, iteration: Iteration) -> (Complex<f64>, Complex<f64>) {
+    match iteration.julia_c {
+        Some(julia_c) => (point, julia_c),
+        None => (iteration.initial_z.start(point), point),
+    }
+}
+
+/// The period of the points of the main cardioid and the period 2 bulb, which never
+/// escape and would otherwise use up the whole iteration limit. None for the others.
+///
+/// Only holds for the plain Mandelbrot set, with the L2 norm.
+fn known_period(point: Complex<f64>, iteration: Iteration) -> Option<u32> {
+    let plain_mandelbrot = iteration.kind == FractalKind::Mandelbrot
+        && iteration.power == 2
+        && iteration.julia_c.is_none()
+        && iteration.initial_z == InitialZ::Zero
+        && iteration.norm == EscapeNorm::L2;
+    if !plain_mandelbrot {
+        return None;
+    }
+    let (x, y) = (point.re, point.im);
+    let q = (x - 0.25).powi(2) + y * y;
+    if q * (q + (x - 0.25)) <= 0.25 * y * y {
+        Some(1)
+    } else if (x + 1.0).powi(2) + y * y <= 1.0 / 16.0 {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+const LANES: usize = 4;
+// Rectangles this narrow or short are rendered pixel by pixel instead of subdivided
+const MIN_SUBDIVISION: usize = 8;
+
+/// [`escape_time`] of `LANES` points at once, using SIMD for the iteration.
+///
+/// The lanes keep iterating until all of them escaped, and every lane applies exactly
+/// the same operations as the scalar version, so the results match it bit for bit.
+fn escape_time_lanes(points: [Complex<f64>; LANES], iteration: Iteration) -> [Option<f32>; LANES] {
+    let starts = points.map(|point| starting_values(point, iteration));
+    let mut z_re = f64x4::from(starts.map(|(z, _)| z.re));
+    let mut z_im = f64x4::from(starts.map(|(z, _)| z.im));
+    let c_re = f64x4::from(starts.map(|(_, c)| c.re));
+    let c_im = f64x4::from(starts.map(|(_, c)| c.im));
+    let bailout_squared = f64x4::splat(iteration.bailout_squared);
+    let bailout = f64x4::splat(iteration.bailout_squared.sqrt());
+    let tolerance_squared = f64x4::splat(PERIODICITY_TOLERANCE * PERIODICITY_TOLERANCE);
+    // The reference points of the periodicity check, as `CycleCheck` keeps them
+    let (mut reference_re, mut reference_im) = (z_re, z_im);
+    let mut next_reference = FIRST_REFERENCE;
+
+    let mut counts = [None; LANES];
+    let all_escaped = (1 << LANES) - 1;
+    // The lanes known to be inside the set are done before starting, with no count.
+    let mut escaped = (0..LANES)
+        .filter(|&lane| known_period(points[lane], iteration).is_some())
+        .fold(0, |mask, lane| mask | (1 << lane));
+    if escaped == all_escaped {
+        return counts;
+    }
+    for i in 0..iteration.limit {
+        let escaping = match iteration.norm {
+            EscapeNorm::L2 => (z_re * z_re + z_im * z_im).cmp_ge(bailout_squared),
+            EscapeNorm::LInfinity => z_re.abs().max(z_im.abs()).cmp_ge(bailout),
+            EscapeNorm::L1 => (z_re.abs() + z_im.abs()).cmp_ge(bailout),
+        }
+        .move_mask();
+        let newly_escaped = escaping & !escaped;
+        if newly_escaped != 0 {
+            let (re, im) = (z_re.to_array(), z_im.to_array());
+            for lane in 0..LANES {
+                if newly_escaped & (1 << lane) != 0 {
+                    let z = Complex::new(re[lane], im[lane]);
+                    counts[lane] = Some(smooth_count(i, z, iteration));
+                }
+            }
+            escaped |= newly_escaped;
+            if escaped == all_escaped {
+                break;
+            }
+        }
+
+        if iteration.kind == FractalKind::BurningShip {
+            z_re = z_re.abs();
+            z_im = z_im.abs();
+        }
+        (z_re, z_im) = (
+            z_re * z_re - z_im * z_im + c_re,
+            z_re * z_im + z_im * z_re + c_im,
+        );
+
+        if iteration.periodicity_checking {
+            // The lanes caught in a cycle are done without a count, like the ones known
+            // to be inside the set
+            let (re, im) = (z_re - reference_re, z_im - reference_im);
+            escaped |= (re * re + im * im).cmp_lt(tolerance_squared).move_mask();
+            if escaped == all_escaped {
+                break;
+            }
+            if i + 1 == next_reference {
+                (reference_re, reference_im) = (z_re, z_im);
+                next_reference = next_reference.saturating_mul(2);
+            }
+        }
+    }
+
+    counts
+}
+
+fn smooth_count(count: u32, z: Complex<f64>, iteration: Iteration) -> f32 {
+    // Measured relative to the bailout radius, so the count stays continuous whatever
+    // the radius is. With the L1 norm or a non zero initial z, |z| can still be below the
+    // radius at the escape, which would make the outer logarithm negative or NaN.
+    let log_magnitude = (z.norm().log2() / (0.5 * iteration.bailout_squared.log2())).max(1.0);
+    // The magnitude grows to the power of `power` every iteration, so that's the base
+    // of the outer logarithm. Dividing by log2(2) = 1 keeps the classic case exact.
+    let fraction = log_magnitude.log2() / (iteration.power as f64).log2();
+    (count as f64 + 1.0 - fraction).max(0.0) as f32
+}
+
+/// The point at the center of `pixel` in a render of `bounds` spanning from `upper_left`
+/// to `lower_right`, the same point the shader samples for it.
+pub fn pixel_to_view(
     bounds: (usize, usize),
     pixel: (usize, usize),
     upper_left: Complex<f64>,
     lower_right: Complex<f64>,
+) -> Complex<f64> {
+    position_to_view(
+        bounds,
+        (pixel.0 as f64 + 0.5, pixel.1 as f64 + 0.5),
+        upper_left,
+        lower_right,
+    )
+}
+
+/// The point at `position` in a render of `bounds`, the upper left corners of the pixels
+/// being at whole coordinates.
+fn position_to_view(
+    bounds: (usize, usize),
+    position: (f64, f64),
+    upper_left: Complex<f64>,
+    lower_right: Complex<f64>,
 ) -> Complex<f64> {
     let (width, height) = (
         lower_right.re - upper_left.re,
         upper_left.im - lower_right.im,
     );
     Complex {
-        re: upper_left.re + pixel.0 as f64 * width / bounds.0 as f64,
-        im: upper_left.im - pixel.1 as f64 * height / bounds.1 as f64,
+        re: upper_left.re + position.0 * width / bounds.0 as f64,
+        im: upper_left.im - position.1 * height / bounds.1 as f64,
     }
 }
 
+/// A rectangle of pixels within a render, for rendering only part of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    /// The upper left pixel of the region.
+    pub origin: (usize, usize),
+    pub size: (usize, usize),
+}
+
+impl Region {
+    /// The whole of a render of `bounds`.
+    pub fn whole(bounds: (usize, usize)) -> Self {
+        Region {
+            origin: (0, 0),
+            size: bounds,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.size.0 == 0 || self.size.1 == 0
+    }
+
+    /// The part of the region `size` pixels large, `origin` pixels from its upper left one.
+    fn part(&self, origin: (usize, usize), size: (usize, usize)) -> Region {
+        Region {
+            origin: (self.origin.0 + origin.0, self.origin.1 + origin.1),
+            size,
+        }
+    }
+}
+
+/// Where a region is within the render of `bounds` between `upper_left` and
+/// `lower_right` it's part of.
+#[derive(Debug, Clone, Copy)]
+struct Placement {
+    bounds: (usize, usize),
+    /// The upper left pixel of the region.
+    origin: (usize, usize),
+    upper_left: Complex<f64>,
+    lower_right: Complex<f64>,
+}
+
+impl Placement {
+    /// The point at the center of `pixel` of the region.
+    fn point(&self, pixel: (usize, usize)) -> Complex<f64> {
+        pixel_to_view(
+            self.bounds,
+            (self.origin.0 + pixel.0, self.origin.1 + pixel.1),
+            self.upper_left,
+            self.lower_right,
+        )
+    }
+}
+
+/// Render the pixels of `region` of the render of `bounds` between `upper_left` and
+/// `lower_right` into `counts`, which only holds the region.
 fn render(
-    pixels: &mut [u8],
+    counts: &mut [Option<f32>],
     bounds: (usize, usize),
+    region: Region,
     upper_left: Complex<f64>,
     lower_right: Complex<f64>,
+    iteration: Iteration,
 ) {
-    assert!(pixels.len() == bounds.0 * bounds.1);
+    assert!(counts.len() == region.size.0 * region.size.1);
+    assert!(region.origin.0 + region.size.0 <= bounds.0);
+    assert!(region.origin.1 + region.size.1 <= bounds.1);
+
+    // The points are placed within the whole render, so they come out the same however
+    // it's split into regions
+    let view = Placement {
+        bounds,
+        origin: region.origin,
+        upper_left,
+        lower_right,
+    };
+    let bounds = region.size;
+
+    if iteration.fill_interior {
+        Subdivision {
+            counts,
+            done: vec![false; bounds.0 * bounds.1],
+            bounds,
+            view,
+            iteration,
+        }
+        .rectangle((0, 0), bounds);
+        return;
+    }
+
+    // The lanes only square, orbit traps, higher powers and the periods of the interior
+    // are left to the scalar version
+    if iteration.trap.is_some() || iteration.power != 2 || iteration.interior_periods {
+        for (index, count) in counts.iter_mut().enumerate() {
+            let point = view.point((index % bounds.0, index / bounds.0));
+            *count = escape_time(point, iteration);
+        }
+        return;
+    }
 
+    for row in 0..bounds.1 {
+        let line = &mut counts[row * bounds.0..(row + 1) * bounds.0];
+        let mut chunks = line.chunks_exact_mut(LANES);
+        for (chunk, lanes) in chunks.by_ref().enumerate() {
+            let points = std::array::from_fn(|lane| view.point((chunk * LANES + lane, row)));
+            lanes.copy_from_slice(&escape_time_lanes(points, iteration));
+        }
+        // The pixels at the end of the row which don't fill all the lanes
+        let tail_start = bounds.0 - chunks.into_remainder().len();
+        for column in tail_start..bounds.0 {
+            let point = view.point((column, row));
+            counts[row * bounds.0 + column] = escape_time(point, iteration);
+        }
+    }
+}
+
+/// The Mariani–Silver algorithm, rendering the border of a rectangle and filling its
+/// inside if the whole border is inside the set, or subdividing it into four otherwise.
+struct Subdivision<'a> {
+    counts: &'a mut [Option<f32>],
+    // The pixels rendered or filled already, the borders of neighbouring rectangles
+    // being shared
+    done: Vec<bool>,
+    // The size of the region
+    bounds: (usize, usize),
+    view: Placement,
+    iteration: Iteration,
+}
+
+impl Subdivision<'_> {
+    /// The count of `pixel`, rendering it unless it's done already.
+    fn pixel(&mut self, pixel: (usize, usize)) -> Option<f32> {
+        let index = pixel.1 * self.bounds.0 + pixel.0;
+        if !self.done[index] {
+            let point = self.view.point(pixel);
+            self.counts[index] = escape_time(point, self.iteration);
+            self.done[index] = true;
+        }
+        self.counts[index]
+    }
+
+    /// Render the rectangle of `size` with its upper left pixel at `origin`.
+    fn rectangle(&mut self, origin: (usize, usize), size: (usize, usize)) {
+        let (left, top) = origin;
+        let (right, bottom) = (left + size.0, top + size.1);
+        if size.0 <= MIN_SUBDIVISION || size.1 <= MIN_SUBDIVISION {
+            for row in top..bottom {
+                for column in left..right {
+                    self.pixel((column, row));
+                }
+            }
+            return;
+        }
+
+        // With the periods of the interior, a border within a single component of the
+        // interior has the same period all around, as its inside does
+        let fill = self.pixel((left, top));
+        let mut interior = !escaped(fill);
+        for column in left..right {
+            interior &= self.pixel((column, top)) == fill;
+            interior &= self.pixel((column, bottom - 1)) == fill;
+        }
+        for row in top..bottom {
+            interior &= self.pixel((left, row)) == fill;
+            interior &= self.pixel((right - 1, row)) == fill;
+        }
+        if interior {
+            for row in top + 1..bottom - 1 {
+                let line = row * self.bounds.0;
+                self.counts[line + left + 1..line + right - 1].fill(fill);
+                self.done[line + left + 1..line + right - 1].fill(true);
+            }
+            return;
+        }
+
+        let half = (size.0 / 2, size.1 / 2);
+        self.rectangle((left, top), half);
+        self.rectangle((left + half.0, top), (size.0 - half.0, half.1));
+        self.rectangle((left, top + half.1), (half.0, size.1 - half.1));
+        self.rectangle(
+            (left + half.0, top + half.1),
+            (size.0 - half.0, size.1 - half.1),
+        );
+    }
+}
+
+// Counts above this share of the limit are close to it
+const NEAR_LIMIT: f32 = 0.9;
+// The share of the escaped pixels close to the limit above which it's likely too low
+const NEAR_LIMIT_SHARE: f32 = 0.9;
+
+/// How the escape counts of a render are distributed, for telling whether the iteration
+/// limit is high enough.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CountStats {
+    /// The lowest, highest and average count of the escaped pixels, 0.0 without any.
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    /// The share of all pixels that reached the limit without escaping.
+    pub interior: f32,
+    /// The share of the escaped pixels that escaped within the last tenth of the limit.
+    pub near_limit: f32,
+}
+
+impl CountStats {
+    /// Whether so many of the escaped pixels only escape close to the limit that a higher
+    /// one would likely show more of the boundary, rather than more interior.
+    pub fn limit_too_low(&self) -> bool {
+        self.near_limit > NEAR_LIMIT_SHARE
+    }
+}
+
+impl std::fmt::Display for CountStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "escaped {:.1} to {:.1}, {:.1} on average\n\
+             interior {:.1}%\n\
+             near the limit {:.1}% of the escaped",
+            self.min,
+            self.max,
+            self.mean,
+            100.0 * self.interior,
+            100.0 * self.near_limit
+        )
+    }
+}
+
+/// Whether `count` is the count of an escaped point, rather than the negated period of
+/// [`Iteration::interior_periods`] or no count at all.
+pub fn escaped(count: Option<f32>) -> bool {
+    count.is_some_and(|count| count >= 0.0)
+}
+
+/// The smoothed escape counts of every pixel of a render, `None` where the point
+/// didn't escape, or its negated period with [`Iteration::interior_periods`].
+///
+/// The buffer is kept between renders and only reallocated when the resolution changes,
+/// so coloring or analyzing the counts doesn't need a fresh allocation every frame.
+#[derive(Debug, Default)]
+pub struct EscapeCounts {
+    bounds: (usize, usize),
+    counts: Vec<Option<f32>>,
+}
+
+impl EscapeCounts {
+    /// Prepare the buffer for a render at `bounds`, keeping the allocation if it's unchanged.
+    pub fn resize(&mut self, bounds: (usize, usize)) {
+        if self.bounds != bounds {
+            self.bounds = bounds;
+            self.counts.resize(bounds.0 * bounds.1, None);
+        }
+    }
+
+    /// The resolution the counts were last resized to.
+    pub fn bounds(&self) -> (usize, usize) {
+        self.bounds
+    }
+
+    /// The counts row by row, starting with the top left pixel.
+    pub fn counts(&self) -> &[Option<f32>] {
+        &self.counts
+    }
+
+    /// Turn the counts upside down, for showing a render with the imaginary axis
+    /// pointing the other way.
+    pub fn flip_rows(&mut self) {
+        let width = self.bounds.0;
+        let rows = self.bounds.1;
+        for row in 0..rows / 2 {
+            let (top, bottom) = self.counts.split_at_mut((rows - 1 - row) * width);
+            top[row * width..(row + 1) * width].swap_with_slice(&mut bottom[..width]);
+        }
+    }
+
+    /// How the counts rendered with the iteration `limit` are distributed.
+    pub fn stats(&self, limit: u32) -> CountStats {
+        let near = NEAR_LIMIT * limit as f32;
+        let (mut min, mut max, mut sum) = (f32::INFINITY, 0.0f32, 0.0f64);
+        let (mut escaped, mut near_limit) = (0, 0);
+        for count in self.counts.iter().flatten().filter(|count| **count >= 0.0) {
+            min = min.min(*count);
+            max = max.max(*count);
+            sum += *count as f64;
+            escaped += 1;
+            if *count >= near {
+                near_limit += 1;
+            }
+        }
+        let share = |part: usize, whole: usize| {
+            if whole == 0 {
+                0.0
+            } else {
+                part as f32 / whole as f32
+            }
+        };
+        CountStats {
+            min: if escaped == 0 { 0.0 } else { min },
+            max,
+            mean: if escaped == 0 {
+                0.0
+            } else {
+                (sum / escaped as f64) as f32
+            },
+            interior: share(self.counts.len() - escaped, self.counts.len()),
+            near_limit: share(near_limit, escaped),
+        }
+    }
+
+    /// Color the counts into the RGB `pixels` in `style`, scaled to the iteration `limit`
+    /// they were rendered with, the interior being black.
+    ///
+    /// The counts are left as they are, so changing the colors doesn't need them to be
+    /// rendered again.
+    pub fn colorize(&self, pixels: &mut [u8], limit: u32, style: &Style) {
+        assert!(pixels.len() == 3 * self.counts.len());
+
+        let equalization = if style.equalize {
+            self.equalization(limit)
+        } else {
+            None
+        };
+        let width = self.bounds.0.max(1);
+        // Every sample of a supersampled pixel gets the threshold of the pixel, so they
+        // average to about the color it would have dithered on its own
+        let factor = style.supersampling.max(1);
+        for (index, (pixel, count)) in pixels.chunks_exact_mut(3).zip(&self.counts).enumerate() {
+            let color = match count {
+                None => [0, 0, 0],
+                Some(count) if *count < 0.0 => period_color(-*count, style),
+                Some(count) => {
+                    let count = match equalization.as_deref() {
+                        Some(below) => equalized(below, *count, limit),
+                        None => *count,
+                    };
+                    let position = style.mapping.position(count, limit).powf(style.gamma);
+                    if style.dither {
+                        let threshold = palette::bayer_threshold(
+                            index % width / factor,
+                            index / width / factor,
+                        );
+                        style
+                            .palette
+                            .dithered_color(position, style.phase, threshold)
+                    } else {
+                        style.palette.cycled_color(position, style.phase)
+                    }
+                }
+            };
+            pixel.copy_from_slice(&color);
+        }
+    }
+
+    /// The cumulative distribution of the counts for histogram equalization, the share of
+    /// the escaped pixels below every count up to `limit`, and below the end of the last
+    /// one. None without any escaped pixels.
+    ///
+    /// Most pixels escape within a narrow band of counts, which a linear mapping to the
+    /// palette squeezes into a few colors. Equalized, every color of the palette covers
+    /// about the same number of pixels, bringing out the filaments near the set.
+    fn equalization(&self, limit: u32) -> Option<Vec<f32>> {
+        let bins = limit as usize + 1;
+        let mut histogram = vec![0; bins];
+        for count in self.counts.iter().flatten().filter(|count| **count >= 0.0) {
+            histogram[(*count as usize).min(bins - 1)] += 1;
+        }
+        let total: usize = histogram.iter().sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut below = Vec::with_capacity(bins + 1);
+        below.push(0.0);
+        let mut running = 0;
+        for bin in histogram {
+            running += bin;
+            below.push(running as f32 / total as f32);
+        }
+        Some(below)
+    }
+}
+
+/// The color of the interior points of `period`, spread over the palette by the golden
+/// ratio so neighbouring periods differ, and darkened to stand apart from the outside.
+fn period_color(period: f32, style: &Style) -> [u8; 3] {
+    let position = (period * 0.618_034).fract();
+    style
+        .palette
+        .cycled_color(position, style.phase)
+        .map(|channel| channel / 2)
+}
+
+/// `count` spread evenly over `0..limit` through the cumulative distribution `below`
+/// of [`EscapeCounts::equalization`].
+fn equalized(below: &[f32], count: f32, limit: u32) -> f32 {
+    let bin = (count as usize).min(below.len() - 2);
+    // Interpolate within the bin to keep the smoothing of the counts
+    let share = below[bin] + (below[bin + 1] - below[bin]) * count.fract();
+    share * limit as f32
+}
+
+/// Average every `factor` × `factor` block of the RGB `pixels` into one pixel, giving
+/// an image of `bounds`.
+pub fn downsample(pixels: Vec<u8>, bounds: (usize, usize), factor: usize) -> Vec<u8> {
+    if factor == 1 {
+        return pixels;
+    }
+    let width = bounds.0 * factor;
+    let mut output = Vec::with_capacity(3 * bounds.0 * bounds.1);
     for row in 0..bounds.1 {
         for column in 0..bounds.0 {
-            let point = pixel_to_point(bounds, (column, row), upper_left, lower_right);
-            pixels[row * bounds.0 + column] = match escape_time(point, 255) {
-                None => 0,
-                Some(count) => 255 - count as u8,
+            let mut sum = [0u32; 3];
+            for sample_row in row * factor..(row + 1) * factor {
+                let start = 3 * (sample_row * width + column * factor);
+                for sample in pixels[start..start + 3 * factor].chunks_exact(3) {
+                    for channel in 0..3 {
+                        sum[channel] += sample[channel] as u32;
+                    }
+                }
             }
+            let samples = (factor * factor) as u32;
+            output.extend(sum.map(|channel| ((channel + samples / 2) / samples) as u8));
+        }
+    }
+    output
+}
+
+/// Repeat every pixel of the RGB `pixels` of `bounds` in a `factor` × `factor` block,
+/// giving an image of `target`, which crops the blocks sticking out past its edges.
+pub fn upscale(
+    pixels: &[u8],
+    bounds: (usize, usize),
+    factor: usize,
+    target: (usize, usize),
+) -> Vec<u8> {
+    let mut output = Vec::with_capacity(3 * target.0 * target.1);
+    for row in 0..target.1 {
+        let line = &pixels[3 * (row / factor) * bounds.0..3 * (row / factor + 1) * bounds.0];
+        for column in 0..target.0 {
+            let start = 3 * (column / factor);
+            output.extend_from_slice(&line[start..start + 3]);
         }
     }
+    output
 }
 
 fn write_image(
@@ -63,37 +1012,427 @@ fn write_image(
         pixels,
         bounds.0 as u32,
         bounds.1 as u32,
-        image::ExtendedColorType::L8,
+        image::ExtendedColorType::Rgb8,
     );
     Ok(())
 }
 
+/// The number of threads to render on when there is no reason to use fewer.
+pub fn available_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|threads| threads.get())
+        .unwrap_or(4)
+}
+
+/// The number of threads to render on, `requested` unless it's 0, in which case all
+/// the available ones.
+pub fn thread_count(requested: usize) -> usize {
+    match requested {
+        0 => available_threads(),
+        requested => requested,
+    }
+}
+
+/// How `render_parallel` distributes the work between the threads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Parallelism {
+    /// One horizontal band of rows per thread.
+    Bands,
+    /// Square tiles handed out to the threads one by one, as they finish the previous one.
+    Tiles,
+    /// Rows distributed between the threads by rayon's work stealing scheduler, which
+    /// keeps every thread busy even when the rows near the set take much longer.
+    #[default]
+    Rayon,
+}
+
+impl Parallelism {
+    pub const ALL: [Parallelism; 3] = [Parallelism::Bands, Parallelism::Tiles, Parallelism::Rayon];
+}
+
+/// Render the region between `upper_left` and `lower_right` into `counts`, at the
+/// resolution it was last resized to, on `threads` threads, distributing the work
+/// as `parallelism` dictates.
+///
+/// A single thread renders everything on the current one, which is easier to debug.
+pub fn render_parallel(
+    counts: &mut EscapeCounts,
+    upper_left: Complex<f64>,
+    lower_right: Complex<f64>,
+    iteration: Iteration,
+    parallelism: Parallelism,
+    threads: usize,
+) {
+    let bounds = counts.bounds;
+    render_region(
+        counts,
+        bounds,
+        Region::whole(bounds),
+        upper_left,
+        lower_right,
+        iteration,
+        parallelism,
+        threads,
+    );
+}
+
+/// Render the region between `upper_left` and `lower_right` into `counts` pixel by pixel
+/// on the current thread, without SIMD or filling the interior, at the resolution it was
+/// last resized to.
+///
+/// This is the plain loop every other way of rendering has to match bit for bit, as a
+/// reference for tests and for debugging the coloring.
+pub fn render_reference(
+    counts: &mut EscapeCounts,
+    upper_left: Complex<f64>,
+    lower_right: Complex<f64>,
+    iteration: Iteration,
+) {
+    let bounds = counts.bounds;
+    for (index, count) in counts.counts.iter_mut().enumerate() {
+        let pixel = (index % bounds.0, index / bounds.0);
+        *count = escape_time(
+            pixel_to_view(bounds, pixel, upper_left, lower_right),
+            iteration,
+        );
+    }
+}
+
+/// Render only `region` of the render of `bounds` between `upper_left` and
+/// `lower_right`, resizing `counts` to the region, like [`render_parallel`] does with
+/// the whole of it.
+#[allow(clippy::too_many_arguments)]
+pub fn render_region(
+    counts: &mut EscapeCounts,
+    bounds: (usize, usize),
+    region: Region,
+    upper_left: Complex<f64>,
+    lower_right: Complex<f64>,
+    iteration: Iteration,
+    parallelism: Parallelism,
+    threads: usize,
+) {
+    counts.resize(region.size);
+    if region.is_empty() {
+        return;
+    }
+    let counts = counts.counts.as_mut_slice();
+    let view = (bounds, upper_left, lower_right);
+    if threads <= 1 {
+        render(counts, bounds, region, upper_left, lower_right, iteration);
+        return;
+    }
+    match parallelism {
+        Parallelism::Bands => render_bands(counts, view, region, iteration, threads),
+        Parallelism::Tiles => render_tiles(counts, view, region, iteration, threads),
+        Parallelism::Rayon => render_rayon(counts, view, region, iteration, threads),
+    }
+}
+
+/// The bounds of a whole render and its upper left and lower right corners, which the
+/// regions rendered in parallel are parts of.
+type View = ((usize, usize), Complex<f64>, Complex<f64>);
+
+fn render_bands(
+    counts: &mut [Option<f32>],
+    (bounds, upper_left, lower_right): View,
+    region: Region,
+    iteration: Iteration,
+    threads: usize,
+) {
+    let width = region.size.0;
+    let rows_per_band = region.size.1 / threads + 1;
+
+    let bands: Vec<&mut [Option<f32>]> = counts.chunks_mut(rows_per_band * width).collect();
+    crossbeam::scope(|spawner| {
+        for (i, band) in bands.into_iter().enumerate() {
+            let band_region = region.part((0, rows_per_band * i), (width, band.len() / width));
+            spawner.spawn(move |_| {
+                render(
+                    band,
+                    bounds,
+                    band_region,
+                    upper_left,
+                    lower_right,
+                    iteration,
+                );
+            });
+        }
+    })
+    .unwrap();
+}
+
+const TILE_SIZE: usize = 64;
+
+fn render_tiles(
+    counts: &mut [Option<f32>],
+    (bounds, upper_left, lower_right): View,
+    region: Region,
+    iteration: Iteration,
+    threads: usize,
+) {
+    // The tiles are laid out from the upper left pixel of the region
+    let size = region.size;
+    let tiles_per_row = size.0.div_ceil(TILE_SIZE);
+    let tile_count = tiles_per_row * size.1.div_ceil(TILE_SIZE);
+    let tile_origin = |tile: usize| {
+        (
+            (tile % tiles_per_row) * TILE_SIZE,
+            (tile / tiles_per_row) * TILE_SIZE,
+        )
+    };
+    let tile_bounds =
+        |(left, top): (usize, usize)| (TILE_SIZE.min(size.0 - left), TILE_SIZE.min(size.1 - top));
+    let next_tile = AtomicUsize::new(0);
+
+    // The tiles aren't contiguous in `counts`, so every thread renders into its own
+    // buffers which are copied into place once all of them are done.
+    let rendered: Vec<Vec<(usize, Vec<Option<f32>>)>> = crossbeam::scope(|spawner| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                spawner.spawn(|_| {
+                    let mut rendered = Vec::new();
+                    loop {
+                        let tile = next_tile.fetch_add(1, Ordering::Relaxed);
+                        if tile >= tile_count {
+                            break;
+                        }
+                        let (left, top) = tile_origin(tile);
+                        let (width, height) = tile_bounds((left, top));
+                        let mut tile_counts = vec![None; width * height];
+                        render(
+                            &mut tile_counts,
+                            bounds,
+                            region.part((left, top), (width, height)),
+                            upper_left,
+                            lower_right,
+                            iteration,
+                        );
+                        rendered.push((tile, tile_counts));
+                    }
+                    rendered
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .collect()
+    })
+    .unwrap();
+
+    for (tile, tile_counts) in rendered.into_iter().flatten() {
+        let (left, top) = tile_origin(tile);
+        let (width, _) = tile_bounds((left, top));
+        for (row, line) in tile_counts.chunks(width).enumerate() {
+            let start = (top + row) * size.0 + left;
+            counts[start..start + width].copy_from_slice(line);
+        }
+    }
+}
+
+/// Escape counts of square tiles of the plane, on a grid of pixels anchored at the origin,
+/// so after panning only the tiles which came into view have to be rendered.
+///
+/// The tiles are only valid for one pixel size and iteration, changing either of them
+/// empties the cache.
+#[derive(Debug, Default)]
+pub struct TileCache {
+    pixel_size: f64,
+    iteration: Option<Iteration>,
+    tiles: HashMap<(i64, i64), Vec<Option<f32>>>,
+}
+
+impl TileCache {
+    /// Render the view with its upper left corner at `upper_left` into `counts`,
+    /// snapped to the pixel grid of the tiles.
+    ///
+    /// The missing tiles are rendered on the global rayon pool, unless `threads` is 1,
+    /// in which case they are rendered on the current thread.
+    pub fn render(
+        &mut self,
+        counts: &mut EscapeCounts,
+        upper_left: Complex<f64>,
+        pixel_size: f64,
+        iteration: Iteration,
+        threads: usize,
+    ) {
+        let never = AtomicBool::new(false);
+        self.render_abortable(counts, upper_left, pixel_size, iteration, threads, &never);
+    }
+
+    /// Render the view like [`TileCache::render`], unless `abort` is set before all of
+    /// the missing tiles are done, which leaves `counts` as they were. Returns whether
+    /// the view was rendered.
+    ///
+    /// `abort` is checked before every tile, and the tiles finished until then are kept,
+    /// so rendering the view again after an abort picks up where it stopped.
+    pub fn render_abortable(
+        &mut self,
+        counts: &mut EscapeCounts,
+        upper_left: Complex<f64>,
+        pixel_size: f64,
+        iteration: Iteration,
+        threads: usize,
+        abort: &AtomicBool,
+    ) -> bool {
+        if self.pixel_size != pixel_size || self.iteration != Some(iteration) {
+            self.tiles.clear();
+            self.pixel_size = pixel_size;
+            self.iteration = Some(iteration);
+        }
+
+        let bounds = counts.bounds;
+        let tile_size = TILE_SIZE as i64;
+        // The position of the view's upper left pixel on the grid, y growing downwards
+        let origin = (
+            (upper_left.re / pixel_size).round() as i64,
+            (-upper_left.im / pixel_size).round() as i64,
+        );
+        let first = (
+            origin.0.div_euclid(tile_size),
+            origin.1.div_euclid(tile_size),
+        );
+        let last = (
+            (origin.0 + bounds.0 as i64 - 1).div_euclid(tile_size),
+            (origin.1 + bounds.1 as i64 - 1).div_euclid(tile_size),
+        );
+
+        let missing: Vec<(i64, i64)> = (first.1..=last.1)
+            .flat_map(|y| (first.0..=last.0).map(move |x| (x, y)))
+            .filter(|tile| !self.tiles.contains_key(tile))
+            .collect();
+        let render_tile = |(x, y): (i64, i64)| {
+            if abort.load(Ordering::Relaxed) {
+                return None;
+            }
+            let tile_upper_left = Complex::new(
+                (x * tile_size) as f64 * pixel_size,
+                -((y * tile_size) as f64) * pixel_size,
+            );
+            let tile_lower_right = Complex::new(
+                ((x + 1) * tile_size) as f64 * pixel_size,
+                -(((y + 1) * tile_size) as f64) * pixel_size,
+            );
+            let mut tile_counts = vec![None; TILE_SIZE * TILE_SIZE];
+            render(
+                &mut tile_counts,
+                (TILE_SIZE, TILE_SIZE),
+                Region::whole((TILE_SIZE, TILE_SIZE)),
+                tile_upper_left,
+                tile_lower_right,
+                iteration,
+            );
+            Some(((x, y), tile_counts))
+        };
+        let missing_count = missing.len();
+        let rendered: Vec<_> = if threads <= 1 {
+            missing.into_iter().map_while(render_tile).collect()
+        } else {
+            missing.into_par_iter().filter_map(render_tile).collect()
+        };
+        let complete = rendered.len() == missing_count;
+        self.tiles.extend(rendered);
+        if !complete {
+            return false;
+        }
+
+        for row in 0..bounds.1 {
+            let y = origin.1 + row as i64;
+            let tile_row = y.rem_euclid(tile_size) as usize;
+            let mut column = 0;
+            while column < bounds.0 {
+                let x = origin.0 + column as i64;
+                let tile_column = x.rem_euclid(tile_size) as usize;
+                let width = (TILE_SIZE - tile_column).min(bounds.0 - column);
+                let tile = &self.tiles[&(x.div_euclid(tile_size), y.div_euclid(tile_size))];
+                let start = tile_row * TILE_SIZE + tile_column;
+                let target = row * bounds.0 + column;
+                counts.counts[target..target + width].copy_from_slice(&tile[start..start + width]);
+                column += width;
+            }
+        }
+
+        // Forget the tiles far out of view, so panning around doesn't grow the cache forever.
+        const MARGIN: i64 = 2;
+        self.tiles.retain(|&(x, y), _| {
+            (first.0 - MARGIN..=last.0 + MARGIN).contains(&x)
+                && (first.1 - MARGIN..=last.1 + MARGIN).contains(&y)
+        });
+        true
+    }
+}
+
+fn render_rayon(
+    counts: &mut [Option<f32>],
+    view: View,
+    region: Region,
+    iteration: Iteration,
+    threads: usize,
+) {
+    // Building a new pool for every frame is wasted work when the global one fits.
+    if threads == rayon::current_num_threads() {
+        render_rows(counts, view, region, iteration);
+        return;
+    }
+    match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+        Ok(pool) => pool.install(|| render_rows(counts, view, region, iteration)),
+        // Without threads to spare, render everything on the current one.
+        Err(_) => {
+            let (bounds, upper_left, lower_right) = view;
+            render(counts, bounds, region, upper_left, lower_right, iteration)
+        }
+    }
+}
+
+/// Render the rows of the region in parallel on the current rayon pool.
+///
+/// Filling the interior needs rectangles to subdivide, so the rows are rendered in
+/// bands of a tile's height then.
+fn render_rows(
+    counts: &mut [Option<f32>],
+    (bounds, upper_left, lower_right): View,
+    region: Region,
+    iteration: Iteration,
+) {
+    let rows = if iteration.fill_interior {
+        TILE_SIZE
+    } else {
+        1
+    };
+    let width = region.size.0;
+    counts
+        .par_chunks_mut(rows * width)
+        .enumerate()
+        .for_each(|(band, lines)| {
+            let band_region = region.part((0, band * rows), (width, lines.len() / width));
+            render(
+                lines,
+                bounds,
+                band_region,
+                upper_left,
+                lower_right,
+                iteration,
+            );
+        });
+}
+
+#[allow(dead_code)]
 pub fn run() {
     let upper_left = Complex { re: -1.2, im: 0.35 };
     let lower_right = Complex { re: -1.0, im: 0.2 };
-    let mut pixels = vec![0; 4000 * 3000];
-
     let bounds = (4000, 3000);
-    let threads = 8;
-    let rows_per_band = bounds.1 / threads + 1;
-
-    {
-        let bands: Vec<&mut [u8]> = pixels.chunks_mut(rows_per_band * bounds.0).collect();
-        crossbeam::scope(|spawner| {
-            for (i, band) in bands.into_iter().enumerate() {
-                let top = rows_per_band * i;
-                let height = band.len() / bounds.0;
-                let band_bounds = (bounds.0, height);
-                let band_upper_left = pixel_to_point(bounds, (0, top), upper_left, lower_right);
-                let band_lower_right =
-                    pixel_to_point(bounds, (bounds.0, top + height), upper_left, lower_right);
-
-                spawner.spawn(move |_| {
-                    render(band, band_bounds, band_upper_left, band_lower_right);
-                });
-            }
-        })
-        .unwrap();
-    }
+    let mut counts = EscapeCounts::default();
+    counts.resize(bounds);
+    render_parallel(
+        &mut counts,
+        upper_left,
+        lower_right,
+        Iteration::default(),
+        Parallelism::Bands,
+        8,
+    );
+    let mut pixels = vec![0; 3 * bounds.0 * bounds.1];
+    counts.colorize(&mut pixels, DEFAULT_ITERATION_LIMIT, &Style::default());
     let _ = write_image("first_mandi.png", &pixels, bounds);
 }
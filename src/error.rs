@@ -0,0 +1,178 @@
+use std::fmt;
+
+/// Everything that can go wrong while setting up, rendering or exporting.
+#[derive(Debug)]
+pub enum Error {
+    EventLoop(winit::error::EventLoopError),
+    Window(winit::error::OsError),
+    CreateSurface(wgpu::CreateSurfaceError),
+    NoAdapter,
+    // The adapter asked for isn't one of the available ones, which the reason lists
+    Adapter(String),
+    RequestDevice(wgpu::RequestDeviceError),
+    // The adapter can't present to the window's surface
+    UnsupportedSurface,
+    Surface(wgpu::SurfaceError),
+    SoftBuffer(softbuffer::SoftBufferError),
+    Io(std::io::Error),
+    Image(image::ImageError),
+    Gif(gif::EncodingError),
+    // The palette file isn't in the expected format
+    Palette(String),
+    ReadToml(toml::de::Error),
+    WriteToml(toml::ser::Error),
+    // A setting of the config file is out of the range the application works with
+    Config(String),
+    Json(serde_json::Error),
+    // The shared view string can't be parsed
+    View(String),
+    // The render configuration can't be rendered
+    RenderConfig(String),
+    Clipboard(arboard::Error),
+    // A command line argument is unknown or has an invalid value
+    Argument(String),
+    // Reading the pixels of an offscreen render back from the GPU failed
+    Readback(wgpu::BufferAsyncError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::EventLoop(error) => write!(f, "event loop error: {}", error),
+            Error::Window(error) => write!(f, "failed to create the window: {}", error),
+            Error::CreateSurface(error) => write!(f, "failed to create the surface: {}", error),
+            Error::NoAdapter => write!(f, "failed to find an appropriate adapter"),
+            Error::Adapter(reason) => write!(f, "{}", reason),
+            Error::RequestDevice(error) => write!(f, "failed to create the device: {}", error),
+            Error::UnsupportedSurface => write!(f, "the adapter doesn't support the surface"),
+            Error::Surface(error) => write!(f, "failed to acquire the next frame: {}", error),
+            Error::SoftBuffer(error) => write!(f, "software rendering error: {}", error),
+            Error::Io(error) => write!(f, "{}", error),
+            Error::Image(error) => write!(f, "failed to write the image: {}", error),
+            Error::Gif(error) => write!(f, "failed to write the GIF: {}", error),
+            Error::Palette(reason) => write!(f, "failed to load the palette: {}", reason),
+            Error::ReadToml(error) => write!(f, "failed to read the TOML file: {}", error),
+            Error::WriteToml(error) => write!(f, "failed to write the TOML file: {}", error),
+            Error::Config(reason) => write!(f, "invalid config file: {}", reason),
+            Error::Json(error) => write!(f, "failed to read or write the JSON file: {}", error),
+            Error::View(reason) => write!(f, "invalid view string: {}", reason),
+            Error::RenderConfig(reason) => write!(f, "invalid render configuration: {}", reason),
+            Error::Clipboard(error) => write!(f, "failed to access the clipboard: {}", error),
+            Error::Argument(reason) => write!(f, "{}", reason),
+            Error::Readback(error) => write!(f, "failed to read back the render: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::EventLoop(error) => Some(error),
+            Error::Window(error) => Some(error),
+            Error::CreateSurface(error) => Some(error),
+            Error::NoAdapter
+            | Error::Adapter(_)
+            | Error::UnsupportedSurface
+            | Error::Palette(_)
+            | Error::Config(_)
+            | Error::View(_)
+            | Error::RenderConfig(_)
+            | Error::Argument(_) => None,
+            Error::RequestDevice(error) => Some(error),
+            Error::Surface(error) => Some(error),
+            Error::SoftBuffer(error) => Some(error),
+            Error::Io(error) => Some(error),
+            Error::Image(error) => Some(error),
+            Error::Gif(error) => Some(error),
+            Error::ReadToml(error) => Some(error),
+            Error::WriteToml(error) => Some(error),
+            Error::Json(error) => Some(error),
+            Error::Clipboard(error) => Some(error),
+            Error::Readback(error) => Some(error),
+        }
+    }
+}
+
+impl From<winit::error::EventLoopError> for Error {
+    fn from(error: winit::error::EventLoopError) -> Self {
+        Error::EventLoop(error)
+    }
+}
+
+impl From<winit::error::OsError> for Error {
+    fn from(error: winit::error::OsError) -> Self {
+        Error::Window(error)
+    }
+}
+
+impl From<wgpu::CreateSurfaceError> for Error {
+    fn from(error: wgpu::CreateSurfaceError) -> Self {
+        Error::CreateSurface(error)
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for Error {
+    fn from(error: wgpu::RequestDeviceError) -> Self {
+        Error::RequestDevice(error)
+    }
+}
+
+impl From<wgpu::SurfaceError> for Error {
+    fn from(error: wgpu::SurfaceError) -> Self {
+        Error::Surface(error)
+    }
+}
+
+impl From<softbuffer::SoftBufferError> for Error {
+    fn from(error: softbuffer::SoftBufferError) -> Self {
+        Error::SoftBuffer(error)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(error: image::ImageError) -> Self {
+        Error::Image(error)
+    }
+}
+
+impl From<gif::EncodingError> for Error {
+    fn from(error: gif::EncodingError) -> Self {
+        Error::Gif(error)
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(error: toml::de::Error) -> Self {
+        Error::ReadToml(error)
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(error: toml::ser::Error) -> Self {
+        Error::WriteToml(error)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Json(error)
+    }
+}
+
+impl From<arboard::Error> for Error {
+    fn from(error: arboard::Error) -> Self {
+        Error::Clipboard(error)
+    }
+}
+
+impl From<wgpu::BufferAsyncError> for Error {
+    fn from(error: wgpu::BufferAsyncError) -> Self {
+        Error::Readback(error)
+    }
+}
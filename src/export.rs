@@ -0,0 +1,407 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use color_quant::NeuQuant;
+use gif::{Encoder, Frame, Repeat};
+use image::ExtendedColorType;
+use num::Complex;
+use serde::Serialize;
+
+use crate::bookmarks::Bookmark;
+use crate::cpu::{self, ColorMapping, EscapeCounts, Iteration, Parallelism};
+use crate::error::Error;
+use crate::palette::Palette;
+use crate::YAxis;
+
+/// The part of the complex plane shown by a single frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub center: (f64, f64),
+    pub zoom: f64,
+}
+
+/// How a view is turned into pixels, beyond the iteration itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Style {
+    pub palette: Palette,
+    /// Histogram equalize the counts before coloring them.
+    pub equalize: bool,
+    /// Every pixel averages the colors of `supersampling`² samples, 1 meaning one sample.
+    pub supersampling: usize,
+    /// How far the colors are cycled through the palette, from 0.0 to 1.0.
+    pub phase: f32,
+    /// The counts scaled to the range from 0.0 to 1.0 are raised to this power before
+    /// looking up their colors. Below 1.0 brightens the low counts of the filaments.
+    pub gamma: f32,
+    pub y_axis: YAxis,
+    /// Dither the colors with a Bayer matrix, against banding in gentle gradients.
+    pub dither: bool,
+    pub mapping: ColorMapping,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            palette: Palette::default(),
+            equalize: false,
+            supersampling: 1,
+            phase: 0.0,
+            gamma: 1.0,
+            y_axis: YAxis::default(),
+            dither: false,
+            mapping: ColorMapping::default(),
+        }
+    }
+}
+
+pub struct GifSettings {
+    pub frames: usize,
+    // Delay between two frames in milliseconds, GIF stores it in 10ms units.
+    pub delay_ms: u16,
+    pub resolution: (usize, usize),
+    pub threads: usize,
+}
+
+impl Default for GifSettings {
+    fn default() -> Self {
+        GifSettings {
+            frames: 60,
+            delay_ms: 50,
+            resolution: (480, 360),
+            threads: 8,
+        }
+    }
+}
+
+pub struct FrameSettings {
+    pub frames: usize,
+    /// The frame rate the frames are meant to be played back at, only written into
+    /// the file names for encoding them into a video.
+    pub fps: u32,
+    pub resolution: (usize, usize),
+    pub threads: usize,
+}
+
+impl Default for FrameSettings {
+    fn default() -> Self {
+        FrameSettings {
+            frames: 300,
+            fps: 30,
+            resolution: (1920, 1080),
+            threads: cpu::available_threads(),
+        }
+    }
+}
+
+/// Interpolate between the `from` and `to` cameras, `t` going from 0.0 to 1.0.
+///
+/// The zoom is interpolated geometrically, so every frame zooms in by the same factor.
+/// The center follows the change in the visible extent instead of moving linearly,
+/// otherwise the target would drift out of view while zooming in.
+pub fn interpolate_camera(from: Camera, to: Camera, t: f64) -> Camera {
+    let zoom = from.zoom * (to.zoom / from.zoom).powf(t);
+    let progress = if from.zoom == to.zoom {
+        t
+    } else {
+        (1.0 / from.zoom - 1.0 / zoom) / (1.0 / from.zoom - 1.0 / to.zoom)
+    };
+    Camera {
+        center: (
+            from.center.0 + (to.center.0 - from.center.0) * progress,
+            from.center.1 + (to.center.1 - from.center.1) * progress,
+        ),
+        zoom,
+    }
+}
+
+/// Render the escape counts of the view of `camera` into `counts` on the CPU, at
+/// `bounds`, with the imaginary axis pointing along `y_axis`.
+pub fn render_counts(
+    counts: &mut EscapeCounts,
+    bounds: (usize, usize),
+    camera: Camera,
+    iteration: Iteration,
+    y_axis: YAxis,
+    threads: usize,
+) {
+    let resolution = winit::dpi::PhysicalSize::new(bounds.0 as u32, bounds.1 as u32);
+    let (top_left, view_resolution) =
+        crate::center_to_start_conditions(camera.center, camera.zoom, resolution, y_axis);
+    let upper_left = Complex::new(top_left.0, top_left.1);
+    let lower_right = Complex::new(
+        top_left.0 + view_resolution.0,
+        top_left.1 - view_resolution.1,
+    );
+    counts.resize(bounds);
+    cpu::render_parallel(
+        counts,
+        upper_left,
+        lower_right,
+        iteration,
+        Parallelism::default(),
+        threads,
+    );
+}
+
+/// Render the view of `camera` into `counts` on the CPU, at `bounds`, as RGB pixels
+/// in the given `style`.
+pub fn render_view(
+    counts: &mut EscapeCounts,
+    bounds: (usize, usize),
+    camera: Camera,
+    iteration: Iteration,
+    style: &Style,
+    threads: usize,
+) -> Vec<u8> {
+    // The samples are rendered as the pixels of a larger image, averaged at the end
+    let samples = (
+        bounds.0 * style.supersampling,
+        bounds.1 * style.supersampling,
+    );
+    render_counts(counts, samples, camera, iteration, style.y_axis, threads);
+    let mut pixels = vec![0; 3 * samples.0 * samples.1];
+    counts.colorize(&mut pixels, iteration.limit, style);
+    cpu::downsample(pixels, bounds, style.supersampling)
+}
+
+/// Render every frame of the zoom from `from` to `to` on the CPU.
+fn render_zoom_frames(
+    from: Camera,
+    to: Camera,
+    iteration: Iteration,
+    style: &Style,
+    settings: &GifSettings,
+) -> Vec<Vec<u8>> {
+    let mut counts = EscapeCounts::default();
+    (0..settings.frames)
+        .map(|frame| {
+            let camera = interpolate_camera(from, to, progress(frame, settings.frames));
+            let pixels = render_view(
+                &mut counts,
+                settings.resolution,
+                camera,
+                iteration,
+                style,
+                settings.threads,
+            );
+            println!("Rendered GIF frame {}/{}", frame + 1, settings.frames);
+            pixels
+        })
+        .collect()
+}
+
+/// How far along the animation of `frames` frames `frame` is, from 0.0 to 1.0.
+fn progress(frame: usize, frames: usize) -> f64 {
+    if frames > 1 {
+        frame as f64 / (frames - 1) as f64
+    } else {
+        1.0
+    }
+}
+
+/// Render the view of `camera` at `resolution` on the CPU and save it as a PNG.
+pub fn export_png(
+    filename: &str,
+    camera: Camera,
+    iteration: Iteration,
+    style: &Style,
+    resolution: (usize, usize),
+    threads: usize,
+) -> Result<(), Error> {
+    let mut counts = EscapeCounts::default();
+    let pixels = render_view(&mut counts, resolution, camera, iteration, style, threads);
+    image::save_buffer(
+        filename,
+        &pixels,
+        resolution.0 as u32,
+        resolution.1 as u32,
+        ExtendedColorType::Rgb8,
+    )?;
+    Ok(())
+}
+
+/// What the escape counts saved by [`export_counts`] were rendered from, saved along
+/// with them.
+#[derive(Debug, Serialize)]
+struct CountsInfo<'a> {
+    width: usize,
+    height: usize,
+    /// The iteration limit, above the maximum of the view with adaptive iterations.
+    limit: u32,
+    y_axis: YAxis,
+    // Last, as TOML tables can't be followed by plain values
+    view: &'a Bookmark,
+}
+
+/// Render the escape counts of `view` at `resolution` on the CPU and save them uncolored
+/// into the NumPy file `path`, for processing them elsewhere.
+///
+/// The counts are a height × width array of float32, NaN inside the set. The view is
+/// saved next to them, in a TOML file of the same name.
+pub fn export_counts(
+    path: &Path,
+    view: &Bookmark,
+    y_axis: YAxis,
+    resolution: (usize, usize),
+    threads: usize,
+) -> Result<(), Error> {
+    let camera = Camera {
+        center: view.center_point,
+        zoom: view.zoom,
+    };
+    let iteration = view.iteration();
+    let mut counts = EscapeCounts::default();
+    render_counts(&mut counts, resolution, camera, iteration, y_axis, threads);
+    write_npy(path, &counts)?;
+
+    let info = CountsInfo {
+        width: resolution.0,
+        height: resolution.1,
+        limit: iteration.limit,
+        y_axis,
+        view,
+    };
+    std::fs::write(path.with_extension("toml"), toml::to_string_pretty(&info)?)?;
+    Ok(())
+}
+
+/// Write `counts` into the version 1.0 NumPy file `path`.
+fn write_npy(path: &Path, counts: &EscapeCounts) -> Result<(), Error> {
+    const MAGIC: &[u8] = b"\x93NUMPY\x01\x00";
+    let (width, height) = counts.bounds();
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+        height, width
+    );
+    // The header ends in a newline, padded with spaces so the data after the magic
+    // string, the header's length and the header starts at a multiple of 64 bytes
+    let length = MAGIC.len() + size_of::<u16>() + header.len() + 1;
+    header.push_str(&" ".repeat(length.next_multiple_of(64) - length));
+    header.push('\n');
+
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(MAGIC)?;
+    file.write_all(&(header.len() as u16).to_le_bytes())?;
+    file.write_all(header.as_bytes())?;
+    for count in counts.counts() {
+        file.write_all(&count.unwrap_or(f32::NAN).to_le_bytes())?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// Render the zoom from `from` to `to` and write it into a single animated GIF.
+///
+/// All frames share one global palette quantized from the pixels of every frame,
+/// so colors don't flicker between frames as they would with per frame palettes.
+pub fn export_zoom_gif(
+    filename: &str,
+    from: Camera,
+    to: Camera,
+    iteration: Iteration,
+    style: &Style,
+    settings: &GifSettings,
+) -> Result<(), Error> {
+    let frames = render_zoom_frames(from, to, iteration, style, settings);
+
+    // Train the quantizer on a sample of every frame, in RGBA as NeuQuant expects it.
+    let sample_step =
+        (frames.len() * settings.resolution.0 * settings.resolution.1 / 100_000).max(1);
+    let samples: Vec<u8> = frames
+        .iter()
+        .flat_map(|pixels| pixels.chunks_exact(3))
+        .step_by(sample_step)
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+        .collect();
+    let quantizer = NeuQuant::new(10, 256, &samples);
+    let gif_palette = quantizer.color_map_rgb();
+    // The frames only use the colors along the palette's gradient, so every color is
+    // looked up once and remembered.
+    let mut lookup = HashMap::new();
+
+    let output = File::create(filename)?;
+    let mut encoder = Encoder::new(
+        output,
+        settings.resolution.0 as u16,
+        settings.resolution.1 as u16,
+        &gif_palette,
+    )?;
+    encoder.set_repeat(Repeat::Infinite)?;
+
+    for pixels in frames {
+        let indices: Vec<u8> = pixels
+            .chunks_exact(3)
+            .map(|pixel| {
+                *lookup
+                    .entry([pixel[0], pixel[1], pixel[2]])
+                    .or_insert_with(|| {
+                        quantizer.index_of(&[pixel[0], pixel[1], pixel[2], 255]) as u8
+                    })
+            })
+            .collect();
+        let frame = Frame {
+            width: settings.resolution.0 as u16,
+            height: settings.resolution.1 as u16,
+            delay: settings.delay_ms / 10,
+            buffer: Cow::Owned(indices),
+            ..Frame::default()
+        };
+        encoder.write_frame(&frame)?;
+    }
+    Ok(())
+}
+
+/// Render the zoom from `from` to `to` into numbered PNGs in `directory`, for encoding
+/// into a video, for example with
+/// `ffmpeg -framerate 30 -i frame_1920x1080_30fps_%05d.png zoom.mp4`.
+///
+/// Every frame is saved as soon as it's rendered, so deep zooms taking hours don't
+/// have to keep all of them in memory, and the finished ones survive an interruption.
+pub fn export_zoom_frames(
+    directory: &Path,
+    from: Camera,
+    to: Camera,
+    iteration: Iteration,
+    style: &Style,
+    settings: &FrameSettings,
+) -> Result<(), Error> {
+    std::fs::create_dir_all(directory)?;
+    let (width, height) = settings.resolution;
+    let mut counts = EscapeCounts::default();
+    for frame in 0..settings.frames {
+        let started = std::time::Instant::now();
+        let camera = interpolate_camera(from, to, progress(frame, settings.frames));
+        let pixels = render_view(
+            &mut counts,
+            settings.resolution,
+            camera,
+            iteration,
+            style,
+            settings.threads,
+        );
+        let path = directory.join(format!(
+            "frame_{}x{}_{}fps_{:05}.png",
+            width, height, settings.fps, frame
+        ));
+        image::save_buffer(
+            &path,
+            &pixels,
+            width as u32,
+            height as u32,
+            ExtendedColorType::Rgb8,
+        )?;
+        println!(
+            "Rendered frame {}/{} at zoom {:.3e} in {:.2?}",
+            frame + 1,
+            settings.frames,
+            camera.zoom,
+            started.elapsed()
+        );
+    }
+    Ok(())
+}
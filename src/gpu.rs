@@ -0,0 +1,1125 @@
+//! Rendering with a fragment shader, into a window or into an image without one.
+use std::{
+    borrow::Cow,
+    mem::offset_of,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use bytemuck::{Pod, Zeroable};
+use serde::{Deserialize, Serialize};
+use wgpu::{
+    BindGroup, BindGroupEntry, BufferBinding, BufferUsages, Device, Queue, RenderPipeline, Surface,
+};
+use winit::{dpi::PhysicalSize, window::Window};
+
+use crate::cpu::{ColorMapping, Iteration};
+use crate::error::Error;
+use crate::export::{Camera, Style};
+use crate::palette::{self, Palette};
+use crate::{RenderParams, YAxis};
+
+/// The texture format of offscreen renders, without the sRGB conversion of window
+/// surfaces, so the colors are the same bytes the CPU renderer writes.
+const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// The `Settings` struct of the shader, field by field.
+///
+/// Uniform buffers align vec2f to 8 bytes and scalars to 4, and every vec2f here already
+/// falls on a multiple of 8, so the struct needs no padding in between. Its size is
+/// rounded up to its 8 byte alignment, which 27 fields of 4 bytes need the padding at the
+/// end for.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+struct Uniforms {
+    upper_left: [f32; 2],
+    width: f32,
+    height: f32,
+    window: [f32; 2],
+    tile_offset: [f32; 2],
+    escape_norm: u32,
+    initial_z: u32,
+    max_iterations: u32,
+    julia: u32,
+    julia_c: [f32; 2],
+    fractal_kind: u32,
+    bailout_squared: f32,
+    supersampling: u32,
+    trap: u32,
+    trap_center: [f32; 2],
+    trap_radius: f32,
+    power: u32,
+    srgb_target: u32,
+    // The coloring, last so it can be written on its own
+    palette_phase: f32,
+    gamma: f32,
+    dither: u32,
+    color_mapping: u32,
+    _padding: u32,
+}
+
+const _: () = assert!(size_of::<Uniforms>() == 112);
+
+/// The point the shader samples at `position` of a window of `resolution`, showing the
+/// view from `top_left` that is `width` × `height` large. Fragment positions are at the
+/// centers of the pixels, half a pixel past their upper left corners.
+///
+/// Mirrors `sample_color` in shader.wgsl, in f64 rather than f32.
+pub fn shader_point(
+    top_left: (f64, f64),
+    (width, height): (f64, f64),
+    resolution: PhysicalSize<u32>,
+    position: (f64, f64),
+) -> (f64, f64) {
+    (
+        top_left.0 + position.0 * width / resolution.width as f64,
+        top_left.1 - position.1 * height / resolution.height as f64,
+    )
+}
+
+/// Everything that decides the pixels of the fractal in the window, besides the palette.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scene {
+    pub camera: Camera,
+    pub iteration: Iteration,
+    pub supersampling: u32,
+    pub y_axis: YAxis,
+    pub phase: f32,
+    pub gamma: f32,
+    pub dither: bool,
+    pub mapping: ColorMapping,
+}
+
+impl Scene {
+    /// The whole pixels the fractal moves by from `self` to `to` in a window of
+    /// `resolution`, as panning with the mouse moves it. None when anything but the center
+    /// changed, or it moved by a fraction of a pixel.
+    fn shift_to(&self, to: &Scene, resolution: PhysicalSize<u32>) -> Option<(i64, i64)> {
+        // The dithering pattern stays in place while the fractal moves
+        let same = Scene {
+            camera: Camera {
+                center: self.camera.center,
+                ..to.camera
+            },
+            ..*to
+        };
+        if *self != same || self.dither {
+            return None;
+        }
+        let (_, (width, height)) = crate::center_to_start_conditions(
+            self.camera.center,
+            self.camera.zoom,
+            resolution,
+            self.y_axis,
+        );
+        // The height is negative while the imaginary axis points down, which turns the
+        // direction the fractal moves in along with it
+        let shift = (
+            (self.camera.center.0 - to.camera.center.0) * resolution.width as f64 / width,
+            (to.camera.center.1 - self.camera.center.1) * resolution.height as f64 / height,
+        );
+        let whole = |pixels: f64| (pixels - pixels.round()).abs() < 1.0e-3;
+        (whole(shift.0) && whole(shift.1)).then(|| (shift.0.round() as i64, shift.1.round() as i64))
+    }
+}
+
+/// The fractal as last drawn into the window, kept so that panning only renders the
+/// strips it uncovers, the rest being copied over from the last frame.
+struct Canvas {
+    // Drawn into in turns, as copies within a texture can't overlap
+    textures: [wgpu::Texture; 2],
+    current: usize,
+    size: PhysicalSize<u32>,
+    // What the current texture shows, None until it's drawn into
+    scene: Option<Scene>,
+}
+
+impl Canvas {
+    fn new(device: &Device, size: PhysicalSize<u32>, format: wgpu::TextureFormat) -> Self {
+        let texture = || {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("canvas_texture"),
+                size: wgpu::Extent3d {
+                    width: size.width,
+                    height: size.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::COPY_SRC
+                    | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            })
+        };
+        Canvas {
+            textures: [texture(), texture()],
+            current: 0,
+            size,
+            scene: None,
+        }
+    }
+
+    /// Move the fractal drawn last by `shift` pixels into the other texture, and render
+    /// the strips along the edges it leaves uncovered there.
+    fn pan(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &Pipeline,
+        shift: (i64, i64),
+        timestamps: Option<&Timestamps>,
+    ) {
+        let (width, height) = (self.size.width as i64, self.size.height as i64);
+        let next = 1 - self.current;
+        // A move across the whole window leaves nothing to copy
+        if shift.0.abs() < width && shift.1.abs() < height {
+            let copy = |origin: (i64, i64)| wgpu::ImageCopyTexture {
+                origin: wgpu::Origin3d {
+                    x: origin.0 as u32,
+                    y: origin.1 as u32,
+                    z: 0,
+                },
+                ..self.textures[0].as_image_copy()
+            };
+            encoder.copy_texture_to_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &self.textures[self.current],
+                    ..copy(((-shift.0).max(0), (-shift.1).max(0)))
+                },
+                wgpu::ImageCopyTexture {
+                    texture: &self.textures[next],
+                    ..copy((shift.0.max(0), shift.1.max(0)))
+                },
+                wgpu::Extent3d {
+                    width: (width - shift.0.abs()) as u32,
+                    height: (height - shift.1.abs()) as u32,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        // The strips as x, y, width and height, overlapping in the corner
+        let mut strips = Vec::new();
+        let (across, down) = (shift.0.abs().min(width), shift.1.abs().min(height));
+        if shift.0 > 0 {
+            strips.push([0, 0, across, height]);
+        } else if shift.0 < 0 {
+            strips.push([width - across, 0, across, height]);
+        }
+        if shift.1 > 0 {
+            strips.push([0, 0, width, down]);
+        } else if shift.1 < 0 {
+            strips.push([0, height - down, width, down]);
+        }
+        let strips: Vec<[u32; 4]> = strips
+            .into_iter()
+            .map(|strip| strip.map(|value| value as u32))
+            .collect();
+        self.current = next;
+        let view = self.textures[next].create_view(&wgpu::TextureViewDescriptor::default());
+        draw_pass(encoder, pipeline, &view, Some(&strips), timestamps);
+    }
+
+    /// Render the whole fractal into the current texture.
+    fn redraw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &Pipeline,
+        timestamps: Option<&Timestamps>,
+    ) {
+        let view = self.textures[self.current].create_view(&wgpu::TextureViewDescriptor::default());
+        draw_pass(encoder, pipeline, &view, None, timestamps);
+    }
+}
+
+/// What has to be rendered of the fractal in the window for the next frame.
+enum Update {
+    /// Nothing, it's still the same.
+    Keep,
+    /// The strips uncovered by panning it by whole pixels.
+    Pan((i64, i64)),
+    Redraw,
+}
+
+/// Render the fractal into `view`, all of it cleared to black first, or only the `strips`
+/// given as x, y, width and height on top of what's there.
+fn draw_pass(
+    encoder: &mut wgpu::CommandEncoder,
+    pipeline: &Pipeline,
+    view: &wgpu::TextureView,
+    strips: Option<&[[u32; 4]]>,
+    timestamps: Option<&Timestamps>,
+) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("render_pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: match strips {
+                    Some(_) => wgpu::LoadOp::Load,
+                    None => wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                },
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: timestamps.map(Timestamps::writes),
+        occlusion_query_set: None,
+    });
+    match strips {
+        Some(strips) => {
+            for &[x, y, width, height] in strips {
+                render_pass.set_scissor_rect(x, y, width, height);
+                pipeline.draw(&mut render_pass);
+            }
+        }
+        None => pipeline.draw(&mut render_pass),
+    }
+}
+
+/// Which kind of GPU wgpu picks when there are several and none is named.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PowerPreference {
+    /// Whichever wgpu picks on its own.
+    #[default]
+    Default,
+    /// The integrated GPU of a laptop, sparing the battery.
+    LowPower,
+    /// The discrete GPU.
+    HighPerformance,
+}
+
+impl PowerPreference {
+    fn wgpu(self) -> wgpu::PowerPreference {
+        match self {
+            PowerPreference::Default => wgpu::PowerPreference::default(),
+            PowerPreference::LowPower => wgpu::PowerPreference::LowPower,
+            PowerPreference::HighPerformance => wgpu::PowerPreference::HighPerformance,
+        }
+    }
+}
+
+impl FromStr for PowerPreference {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "default" => Ok(PowerPreference::Default),
+            "low-power" => Ok(PowerPreference::LowPower),
+            "high-performance" => Ok(PowerPreference::HighPerformance),
+            _ => Err(Error::Argument(format!(
+                "unknown power preference {}, it's one of default, low-power and \
+                 high-performance",
+                text
+            ))),
+        }
+    }
+}
+
+/// Which GPU to render on.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AdapterChoice {
+    pub power_preference: PowerPreference,
+    /// The index of the adapter in the list printed when none matches, or a part of its
+    /// name in any case, taken regardless of the power preference.
+    pub adapter: Option<String>,
+}
+
+impl AdapterChoice {
+    /// Find the adapter chosen, one that can present to `surface` if there is one, and
+    /// print which one it is.
+    async fn adapter(
+        &self,
+        instance: &wgpu::Instance,
+        surface: Option<&Surface<'_>>,
+    ) -> Result<wgpu::Adapter, Error> {
+        let adapter = match self.adapter.as_deref() {
+            None => instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: self.power_preference.wgpu(),
+                    force_fallback_adapter: false,
+                    compatible_surface: surface,
+                })
+                .await
+                .ok_or(Error::NoAdapter)?,
+            Some(wanted) => {
+                let mut adapters = instance.enumerate_adapters(wgpu::Backends::all());
+                let name = wanted.to_lowercase();
+                let position = match wanted.parse::<usize>() {
+                    Ok(index) => (index < adapters.len()).then_some(index),
+                    Err(_) => adapters
+                        .iter()
+                        .position(|adapter| adapter.get_info().name.to_lowercase().contains(&name)),
+                };
+                let Some(position) = position else {
+                    return Err(Error::Adapter(format!(
+                        "no adapter matches {}, the available ones are:\n{}",
+                        wanted,
+                        list_adapters(&adapters)
+                    )));
+                };
+                let adapter = adapters.swap_remove(position);
+                if surface.is_some_and(|surface| !adapter.is_surface_supported(surface)) {
+                    return Err(Error::UnsupportedSurface);
+                }
+                adapter
+            }
+        };
+        println!(
+            "Using the adapter {}",
+            describe_adapter(&adapter.get_info())
+        );
+        Ok(adapter)
+    }
+}
+
+/// The name of an adapter, followed by its backend and what kind of GPU it is.
+fn describe_adapter(info: &wgpu::AdapterInfo) -> String {
+    format!("{} ({:?}, {:?})", info.name, info.backend, info.device_type)
+}
+
+/// Every one of `adapters` on a line of its own, after the index that chooses it.
+fn list_adapters(adapters: &[wgpu::Adapter]) -> String {
+    if adapters.is_empty() {
+        return "  none".to_string();
+    }
+    adapters
+        .iter()
+        .enumerate()
+        .map(|(index, adapter)| format!("  {}: {}", index, describe_adapter(&adapter.get_info())))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub struct Wgpu {
+    pub surface: Surface<'static>,
+    pub config: wgpu::SurfaceConfiguration,
+    pub device: Device,
+    pub queue: Queue,
+    pub pipeline: Pipeline,
+    // None when the GPU doesn't support timestamp queries
+    pub timestamps: Option<Timestamps>,
+    // None until the first frame, and when the surface can't be copied into, in which
+    // case every frame is rendered whole
+    canvas: Option<Canvas>,
+}
+
+impl Wgpu {
+    /// Set up the GPU of `choice` for rendering into `window`.
+    pub async fn new(window: Arc<Window>, choice: &AdapterChoice) -> Result<Self, Error> {
+        let instance = wgpu::Instance::default();
+        let window_size = window.inner_size();
+        let surface = instance.create_surface(window)?;
+        // Request an adapter that can support our surface
+        let adapter = choice.adapter(&instance, Some(&surface)).await?;
+        let (device, queue) = request_device(&adapter).await?;
+        println!("Prepared device: {:?}", device);
+
+        // Configure surface
+        let mut config = surface
+            .get_default_config(&adapter, window_size.width, window_size.height)
+            .ok_or(Error::UnsupportedSurface)?;
+        let swapchain_capabilities = surface.get_capabilities(&adapter);
+        // Copying the fractal drawn last into the surface saves rendering all of it again
+        // while panning
+        if swapchain_capabilities
+            .usages
+            .contains(wgpu::TextureUsages::COPY_DST)
+        {
+            config.usage |= wgpu::TextureUsages::COPY_DST;
+        }
+        surface.configure(&device, &config);
+
+        let swapchain_format = swapchain_capabilities.formats[0];
+        let pipeline = Pipeline::new(&device, swapchain_format);
+        let timestamps = Timestamps::new(&device, &queue);
+
+        Ok(Wgpu {
+            surface,
+            config,
+            device,
+            queue,
+            pipeline,
+            timestamps,
+            canvas: None,
+        })
+    }
+
+    /// Upload `palette` for the shader to color with.
+    pub fn write_palette(&mut self, palette: &Palette) {
+        self.pipeline.write_palette(&self.queue, palette);
+        if let Some(canvas) = self.canvas.as_mut() {
+            canvas.scene = None;
+        }
+    }
+
+    /// Reconfigure the surface for the new window `size`.
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        // A minimized window can be zero sized, which a surface can't be configured to.
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
+        self.config.width = size.width;
+        self.config.height = size.height;
+        self.surface.configure(&self.device, &self.config);
+    }
+
+    /// Draw the fractal of `scene` into the surface texture `target`, or the fractal of
+    /// the last frame again when it's None. Timed when `timed` and the GPU supports it,
+    /// returning whether the timestamps are there to be read.
+    ///
+    /// After a pan by whole pixels with nothing else changed, only the strips it uncovers
+    /// are rendered, the rest of the fractal being moved over from the last frame.
+    pub fn draw_fractal(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::Texture,
+        scene: Option<Scene>,
+        timed: bool,
+    ) -> bool {
+        let resolution = PhysicalSize::new(self.config.width, self.config.height);
+        let timestamps = self.timestamps.as_ref().filter(|_| timed);
+        if let Some(scene) = scene {
+            if self.canvas.as_ref().and_then(|canvas| canvas.scene) != Some(scene) {
+                self.pipeline.write_scene(&self.queue, scene, resolution);
+            }
+        }
+        if !self.config.usage.contains(wgpu::TextureUsages::COPY_DST) {
+            let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+            draw_pass(encoder, &self.pipeline, &view, None, timestamps);
+            if let Some(timestamps) = timestamps {
+                timestamps.resolve(encoder);
+            }
+            return timestamps.is_some();
+        }
+
+        if self
+            .canvas
+            .as_ref()
+            .is_some_and(|canvas| canvas.size != resolution)
+        {
+            self.canvas = None;
+        }
+        let canvas = self
+            .canvas
+            .get_or_insert_with(|| Canvas::new(&self.device, resolution, self.config.format));
+        let update = match (scene, canvas.scene) {
+            (None, Some(_)) => Update::Keep,
+            (Some(scene), Some(shown)) if scene == shown => Update::Keep,
+            (Some(scene), Some(shown)) => match shown.shift_to(&scene, resolution) {
+                Some(shift) => Update::Pan(shift),
+                None => Update::Redraw,
+            },
+            (_, None) => Update::Redraw,
+        };
+        let rendered = !matches!(update, Update::Keep);
+        match update {
+            Update::Keep => (),
+            Update::Pan(shift) => canvas.pan(encoder, &self.pipeline, shift, timestamps),
+            Update::Redraw => canvas.redraw(encoder, &self.pipeline, timestamps),
+        }
+        if rendered {
+            canvas.scene = scene;
+        }
+        encoder.copy_texture_to_texture(
+            canvas.textures[canvas.current].as_image_copy(),
+            target.as_image_copy(),
+            wgpu::Extent3d {
+                width: resolution.width,
+                height: resolution.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        if let Some(timestamps) = timestamps.filter(|_| rendered) {
+            timestamps.resolve(encoder);
+        }
+        rendered && timestamps.is_some()
+    }
+}
+
+/// A GPU without a window, rendering into images read back into memory.
+pub struct Offscreen {
+    pub device: Device,
+    pub queue: Queue,
+    pub pipeline: Pipeline,
+    // None when the GPU doesn't support timestamp queries
+    pub timestamps: Option<Timestamps>,
+    /// The largest width and height rendered in one pass, larger renders being split into
+    /// tiles of at most this size. Lowering it only makes for more tiles.
+    pub max_tile_size: u32,
+}
+
+/// Where the time of an offscreen render went.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderTimes {
+    /// The time the GPU spent in the render pass, None without timestamp queries.
+    pub render: Option<Duration>,
+    /// The rest of the time from submitting the commands to having the pixels in memory,
+    /// mostly copying the image out of the GPU.
+    pub readback: Duration,
+}
+
+impl Offscreen {
+    /// Set up the GPU of `choice` for rendering without a window.
+    pub async fn new(choice: &AdapterChoice) -> Result<Self, Error> {
+        let instance = wgpu::Instance::default();
+        let adapter = choice.adapter(&instance, None).await?;
+        let (device, queue) = request_device(&adapter).await?;
+        let pipeline = Pipeline::new(&device, OFFSCREEN_FORMAT);
+        let timestamps = Timestamps::new(&device, &queue);
+        // Every tile has to fit into a texture, and into the buffer it's read back through.
+        // Tiles a multiple of 64 pixels wide need no padding to align their rows.
+        let limits = device.limits();
+        let by_buffer = ((limits.max_buffer_size / 4) as f64).sqrt() as u32 / 64 * 64;
+        Ok(Offscreen {
+            device,
+            queue,
+            pipeline,
+            timestamps,
+            max_tile_size: limits.max_texture_dimension_2d.min(by_buffer),
+        })
+    }
+
+    /// Render the view of `camera` at `resolution` into RGB pixels in `style`, the same
+    /// layout [`crate::render`] renders into on the CPU.
+    ///
+    /// The shader doesn't equalize the colors, so the equalization of `style` is ignored.
+    pub fn render(
+        &self,
+        camera: Camera,
+        resolution: PhysicalSize<u32>,
+        iteration: Iteration,
+        style: &Style,
+    ) -> Result<Vec<u8>, Error> {
+        self.render_timed(camera, resolution, iteration, style)
+            .map(|(pixels, _)| pixels)
+    }
+
+    /// Render `params` into the caller's 0RGB pixels `out`, as [`crate::render_frame`]
+    /// does on the CPU. The threads of `params` are left to the GPU.
+    ///
+    /// # Panics
+    ///
+    /// When `out` doesn't have exactly as many pixels as `params.resolution`.
+    pub fn render_frame(&self, params: &RenderParams, out: &mut [u32]) -> Result<(), Error> {
+        let (width, height) = params.resolution;
+        assert_eq!(out.len(), width * height);
+        let pixels = self.render(
+            params.camera,
+            PhysicalSize::new(width as u32, height as u32),
+            params.iteration,
+            &params.style,
+        )?;
+        crate::pack_0rgb(&pixels, out);
+        Ok(())
+    }
+
+    /// [`Offscreen::render`], along with how long the rendering and the readback took.
+    ///
+    /// Renders larger than [`Offscreen::max_tile_size`] are rendered tile by tile, every
+    /// tile sampling the same points it would in a single pass.
+    pub fn render_timed(
+        &self,
+        camera: Camera,
+        resolution: PhysicalSize<u32>,
+        iteration: Iteration,
+        style: &Style,
+    ) -> Result<(Vec<u8>, RenderTimes), Error> {
+        self.pipeline.write_palette(&self.queue, &style.palette);
+        let (width, height) = (resolution.width, resolution.height);
+        if width <= self.max_tile_size && height <= self.max_tile_size {
+            return self.render_tile(camera, resolution, (0, 0), resolution, iteration, style);
+        }
+
+        let tile_size = self.max_tile_size.max(1);
+        let mut pixels = vec![0; 3 * width as usize * height as usize];
+        let mut times = RenderTimes {
+            render: self.timestamps.as_ref().map(|_| Duration::ZERO),
+            readback: Duration::ZERO,
+        };
+        for top in (0..height).step_by(tile_size as usize) {
+            for left in (0..width).step_by(tile_size as usize) {
+                let size =
+                    PhysicalSize::new(tile_size.min(width - left), tile_size.min(height - top));
+                let (tile, tile_times) =
+                    self.render_tile(camera, resolution, (left, top), size, iteration, style)?;
+                let row_bytes = 3 * size.width as usize;
+                for (row, line) in tile.chunks_exact(row_bytes).enumerate() {
+                    let start = 3 * ((top as usize + row) * width as usize + left as usize);
+                    pixels[start..start + row_bytes].copy_from_slice(line);
+                }
+                times.render = times.render.zip(tile_times.render).map(|(a, b)| a + b);
+                times.readback += tile_times.readback;
+            }
+        }
+        Ok((pixels, times))
+    }
+
+    /// Render the `tile` at `offset` pixels into the view of `camera` at `resolution` in a
+    /// single pass, with the palette of `style` written already.
+    fn render_tile(
+        &self,
+        camera: Camera,
+        resolution: PhysicalSize<u32>,
+        offset: (u32, u32),
+        tile: PhysicalSize<u32>,
+        iteration: Iteration,
+        style: &Style,
+    ) -> Result<(Vec<u8>, RenderTimes), Error> {
+        self.pipeline.write_settings(
+            &self.queue,
+            camera,
+            resolution,
+            iteration,
+            style.supersampling as u32,
+            style.y_axis,
+        );
+        self.pipeline.write_tile_offset(&self.queue, offset);
+        self.pipeline.write_coloring(
+            &self.queue,
+            style.phase,
+            style.gamma,
+            style.dither,
+            style.mapping,
+        );
+
+        let size = wgpu::Extent3d {
+            width: tile.width,
+            height: tile.height,
+            depth_or_array_layers: 1,
+        };
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("offscreen_texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: OFFSCREEN_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        // Rows of texture copies have to be aligned, the padding is dropped after reading
+        let row_bytes = 4 * tile.width;
+        let padded_row_bytes = row_bytes.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: (padded_row_bytes * tile.height) as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("offscreen_encoder"),
+            });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("offscreen_render_pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: self.timestamps.as_ref().map(Timestamps::writes),
+                occlusion_query_set: None,
+            });
+            self.pipeline.draw(&mut render_pass);
+        }
+        if let Some(timestamps) = self.timestamps.as_ref() {
+            timestamps.resolve(&mut encoder);
+        }
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+        let submitted = Instant::now();
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = readback.slice(..);
+        map_blocking(&self.device, &slice)?;
+        // Copied row by row into a buffer allocated up front, as collecting the bytes one
+        // by one out of nested iterators keeps growing it and can't be vectorized
+        let mut pixels = Vec::with_capacity(3 * (tile.width * tile.height) as usize);
+        for row in slice
+            .get_mapped_range()
+            .chunks_exact(padded_row_bytes as usize)
+        {
+            for pixel in row[..row_bytes as usize].chunks_exact(4) {
+                pixels.extend_from_slice(&pixel[..3]);
+            }
+        }
+        readback.unmap();
+
+        let total = submitted.elapsed();
+        let render = match self.timestamps.as_ref() {
+            Some(timestamps) => Some(timestamps.read(&self.device)?),
+            None => None,
+        };
+        let times = RenderTimes {
+            render,
+            readback: total.saturating_sub(render.unwrap_or_default()),
+        };
+        Ok((pixels, times))
+    }
+}
+
+/// The start and end timestamps of a render pass, for the time the GPU itself spent on
+/// it, rather than the time of the whole frame.
+pub struct Timestamps {
+    query_set: wgpu::QuerySet,
+    // The timestamps are resolved into the first buffer, which can't be mapped, and
+    // copied into the second to be read
+    resolve_buffer: wgpu::Buffer,
+    read_buffer: wgpu::Buffer,
+    // Nanoseconds per timestamp tick
+    period: f32,
+}
+
+impl Timestamps {
+    /// None if `device` doesn't support timestamp queries.
+    pub fn new(device: &Device, queue: &Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        let buffer = |label, usage| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: 2 * wgpu::QUERY_SIZE as u64,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        Some(Timestamps {
+            query_set,
+            resolve_buffer: buffer(
+                "timestamps_resolve",
+                BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+            ),
+            read_buffer: buffer(
+                "timestamps_read",
+                BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            ),
+            period: queue.get_timestamp_period(),
+        })
+    }
+
+    /// The timestamp writes of the render pass to be timed.
+    pub fn writes(&self) -> wgpu::RenderPassTimestampWrites<'_> {
+        wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(0),
+            end_of_pass_write_index: Some(1),
+        }
+    }
+
+    /// Record copying the timestamps of the pass into `encoder`, after the pass, so
+    /// [`Timestamps::read`] can read them once the commands are submitted.
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.read_buffer,
+            0,
+            self.read_buffer.size(),
+        );
+    }
+
+    /// The time between the timestamps, waiting for the GPU to finish the pass.
+    pub fn read(&self, device: &Device) -> Result<Duration, Error> {
+        let slice = self.read_buffer.slice(..);
+        map_blocking(device, &slice)?;
+        let ticks: Vec<u64> = slice
+            .get_mapped_range()
+            .chunks_exact(size_of::<u64>())
+            .map(|bytes| u64::from_ne_bytes(bytes.try_into().unwrap_or_default()))
+            .collect();
+        self.read_buffer.unmap();
+        let nanoseconds = ticks[1].saturating_sub(ticks[0]) as f64 * self.period as f64;
+        Ok(Duration::from_nanos(nanoseconds as u64))
+    }
+}
+
+/// Map `slice` for reading, blocking until the GPU is done writing it.
+fn map_blocking(device: &Device, slice: &wgpu::BufferSlice) -> Result<(), Error> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        // The receiver waits right below, it can't be gone
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver
+        .recv()
+        .map_err(|_| Error::Readback(wgpu::BufferAsyncError))??;
+    Ok(())
+}
+
+/// The shader with its uniforms, shared by the window and the offscreen renderers.
+pub struct Pipeline {
+    pub bind_group: BindGroup,
+    pub uniform_buffer: wgpu::Buffer,
+    pub palette_buffer: wgpu::Buffer,
+    pub render_pipeline: RenderPipeline,
+    // The colors are encoded into sRGB when written, which the dithering has to allow for
+    pub srgb: bool,
+}
+
+impl Pipeline {
+    /// Compile the shader for rendering into textures of `format`.
+    pub fn new(device: &Device, format: wgpu::TextureFormat) -> Self {
+        // Load the shaders
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl"))),
+        });
+
+        // Uniform buffer
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("settings_uniform"),
+            size: size_of::<Uniforms>() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // The palette sampled into RGBA colors, vec4f being the stride of uniform arrays
+        let palette_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("palette_uniform"),
+            size: (palette::GPU_SAMPLES * 4 * size_of::<f32>()) as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Bind group"),
+            entries: &[uniform_entry(0), uniform_entry(1)],
+        });
+
+        // Create bind group
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Settings"),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(BufferBinding {
+                        buffer: &uniform_buffer,
+                        offset: 0,
+                        size: None, // use whole buffer
+                    }),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: palette_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        // Pipeline
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("render_pipeline_descriptor"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(format.into())],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..wgpu::PrimitiveState::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None, // on some platforms it may be good to use such a cache to reduce shader compilation times, otherwise it is handled by most
+        });
+
+        Pipeline {
+            bind_group,
+            uniform_buffer,
+            palette_buffer,
+            render_pipeline,
+            srgb: format.is_srgb(),
+        }
+    }
+
+    /// Draw the full screen quad the shader renders the fractal on.
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..4, 0..1);
+    }
+
+    /// Upload `palette` for the shader to color with.
+    pub fn write_palette(&self, queue: &Queue, palette: &Palette) {
+        let colors = palette
+            .sample(palette::GPU_SAMPLES)
+            .iter()
+            .flat_map(|color| [color[0], color[1], color[2], 255])
+            .flat_map(|channel| (channel as f32 / 255.0).to_ne_bytes())
+            .collect::<Vec<u8>>();
+        queue.write_buffer(&self.palette_buffer, 0, &colors);
+    }
+
+    /// Upload how far the shader cycles the colors through the palette, the gamma, whether
+    /// it dithers the colors and how it spreads the counts over the palette, which are kept
+    /// apart from the other settings so the colors change without changing the view.
+    pub fn write_coloring(
+        &self,
+        queue: &Queue,
+        phase: f32,
+        gamma: f32,
+        dither: bool,
+        mapping: ColorMapping,
+    ) {
+        let offset = offset_of!(Uniforms, palette_phase);
+        let uniforms = Uniforms {
+            palette_phase: phase,
+            gamma,
+            dither: dither as u32,
+            color_mapping: mapping.shader_id(),
+            ..Uniforms::zeroed()
+        };
+        queue.write_buffer(
+            &self.uniform_buffer,
+            offset as u64,
+            &bytemuck::bytes_of(&uniforms)[offset..],
+        );
+    }
+
+    /// Upload where the tile rendered next starts in the whole render, in pixels from its
+    /// upper left corner. The settings written last are those of the whole render, and
+    /// start it at the corner.
+    pub fn write_tile_offset(&self, queue: &Queue, (left, top): (u32, u32)) {
+        let offset = offset_of!(Uniforms, tile_offset);
+        queue.write_buffer(
+            &self.uniform_buffer,
+            offset as u64,
+            bytemuck::bytes_of(&[left as f32, top as f32]),
+        );
+    }
+
+    /// Upload the settings and the coloring of `scene` at `resolution`.
+    pub fn write_scene(&self, queue: &Queue, scene: Scene, resolution: PhysicalSize<u32>) {
+        self.write_settings(
+            queue,
+            scene.camera,
+            resolution,
+            scene.iteration,
+            scene.supersampling,
+            scene.y_axis,
+        );
+        self.write_coloring(queue, scene.phase, scene.gamma, scene.dither, scene.mapping);
+    }
+
+    /// Upload the settings of the shader, for rendering the view of `camera` at
+    /// `resolution` with `supersampling` × `supersampling` samples per pixel, with the
+    /// imaginary axis pointing along `y_axis`.
+    pub fn write_settings(
+        &self,
+        queue: &Queue,
+        camera: Camera,
+        resolution: PhysicalSize<u32>,
+        iteration: Iteration,
+        supersampling: u32,
+        y_axis: YAxis,
+    ) {
+        let (top_left, (width, height)) =
+            crate::center_to_start_conditions(camera.center, camera.zoom, resolution, y_axis);
+
+        // The GPU only works with f32, precision beyond that is left to the CPU renderer.
+        let julia_c = iteration.julia_c.unwrap_or_default();
+        let trap = iteration.trap;
+        let trap_center = trap.map(|trap| trap.center).unwrap_or_default();
+        let uniforms = Uniforms {
+            upper_left: [top_left.0 as f32, top_left.1 as f32],
+            width: width as f32,
+            height: height as f32,
+            window: [resolution.width as f32, resolution.height as f32],
+            escape_norm: iteration.norm.shader_id(),
+            initial_z: iteration.initial_z.shader_id(),
+            max_iterations: iteration.limit,
+            julia: iteration.julia_c.is_some() as u32,
+            julia_c: [julia_c.re as f32, julia_c.im as f32],
+            fractal_kind: iteration.kind.shader_id(),
+            bailout_squared: iteration.bailout_squared as f32,
+            supersampling,
+            trap: trap.map_or(0, |trap| trap.shape.shader_id()),
+            trap_center: [trap_center.re as f32, trap_center.im as f32],
+            trap_radius: trap.map_or(0.0, |trap| trap.radius) as f32,
+            power: iteration.power,
+            srgb_target: self.srgb as u32,
+            ..Uniforms::zeroed()
+        };
+        // The coloring is left as it was written by `write_coloring`
+        let coloring = offset_of!(Uniforms, palette_phase);
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            &bytemuck::bytes_of(&uniforms)[..coloring],
+        );
+    }
+}
+
+/// Create the logical device and command queue of `adapter`.
+async fn request_device(adapter: &wgpu::Adapter) -> Result<(Device, Queue), Error> {
+    let device = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                // Timestamps are only for profiling, and not supported everywhere
+                required_features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
+                // Make sure we use the texture resolution limits from the adapter, so we can support images the size of the swapchain.
+                required_limits: wgpu::Limits::downlevel_defaults()
+                    .using_resolution(adapter.limits()),
+                memory_hints: wgpu::MemoryHints::Performance,
+            },
+            None,
+        )
+        .await?;
+    Ok(device)
+}
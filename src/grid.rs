@@ -0,0 +1,132 @@
+//! Lines along the real and imaginary axes and a light grid at round coordinates, labeled
+//! with their values, for orientation in the complex plane.
+use winit::dpi::PhysicalSize;
+
+use mandelbrot::{center_to_start_conditions, point_to_pixel, YAxis};
+
+use crate::hud;
+
+// The grid lines are at least this far apart at a scale factor of 1.0, leaving room for
+// their labels
+const MIN_SPACING: f64 = 120.0;
+/// The distance of the labels from the edge of the window and from their line, at a scale
+/// factor of 1.0.
+pub const LABEL_OFFSET: f64 = 4.0;
+
+/// The grid lines in view, at multiples of a 1, 2 or 5 times a power of ten step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+    /// The real parts of the vertical lines and their x coordinate in physical pixels.
+    pub vertical: Vec<(f64, f64)>,
+    /// The imaginary parts of the horizontal lines and their y coordinate.
+    pub horizontal: Vec<(f64, f64)>,
+    // The decimals that tell the values of neighbouring lines apart
+    decimals: usize,
+}
+
+impl Grid {
+    /// The grid of the view centered on `center_point` at `zoom`, with its lines spaced
+    /// out so that there are always a few of them in a window of `window_resolution`.
+    pub fn new(
+        center_point: (f64, f64),
+        zoom: f64,
+        window_resolution: PhysicalSize<u32>,
+        scale_factor: f64,
+        y_axis: YAxis,
+    ) -> Self {
+        let (top_left, (view_width, view_height)) =
+            center_to_start_conditions(center_point, zoom, window_resolution, y_axis);
+        let pixel_size = view_width / window_resolution.width as f64;
+        let step = nice_step(MIN_SPACING * scale_factor * pixel_size);
+        let lines = |from: f64, to: f64| {
+            let (first, last) = ((from / step).ceil(), (to / step).floor());
+            // Past the precision of f64, the lines can't be told apart anymore
+            let most = window_resolution.width.max(window_resolution.height) as f64;
+            if !(last - first).is_finite() || last - first > most {
+                return Vec::new();
+            }
+            (first as i64..=last as i64)
+                .map(|line| line as f64 * step)
+                .collect::<Vec<_>>()
+        };
+        let pixel = |point| point_to_pixel(center_point, zoom, window_resolution, y_axis, point);
+        // The bottom of the view is above its top while the imaginary axis points down
+        let bottom = top_left.1 - view_height;
+        Grid {
+            vertical: lines(top_left.0, top_left.0 + view_width)
+                .into_iter()
+                .map(|re| (re, pixel((re, center_point.1)).x))
+                .collect(),
+            horizontal: lines(bottom.min(top_left.1), bottom.max(top_left.1))
+                .into_iter()
+                .map(|im| (im, pixel((center_point.0, im)).y))
+                .collect(),
+            decimals: (-step.log10().floor()).max(0.0) as usize,
+        }
+    }
+
+    /// The label of the line at `value`.
+    pub fn label(&self, value: f64) -> String {
+        format!("{:.*}", self.decimals, value)
+    }
+}
+
+/// The smallest of 1, 2 and 5 times a power of ten that's at least `minimum`.
+fn nice_step(minimum: f64) -> f64 {
+    let magnitude = 10.0_f64.powf(minimum.log10().floor());
+    [1.0, 2.0, 5.0]
+        .into_iter()
+        .map(|multiple| multiple * magnitude)
+        .find(|&step| step >= minimum)
+        .unwrap_or(10.0 * magnitude)
+}
+
+/// Draw `grid` into the 0RGB `buffer` of `bounds`, the axes in white and the other lines
+/// lightening the fractal, with the labels in the bitmap font of the HUD. The vertical
+/// lines are labeled along the top of the window, the horizontal ones along its left.
+pub fn draw(buffer: &mut [u32], bounds: (usize, usize), grid: &Grid, scale_factor: f64) {
+    let offset = LABEL_OFFSET * scale_factor;
+    let label = |position: f64| (position + offset).max(0.0) as usize;
+    let color = |value: f64, pixel: u32| {
+        if value == 0.0 {
+            0xFFFFFF
+        } else {
+            lighten(pixel)
+        }
+    };
+    for &(re, x) in &grid.vertical {
+        let x = x.round();
+        if (0.0..bounds.0 as f64).contains(&x) {
+            for row in 0..bounds.1 {
+                let pixel = &mut buffer[row * bounds.0 + x as usize];
+                *pixel = color(re, *pixel);
+            }
+        }
+    }
+    for &(im, y) in &grid.horizontal {
+        let y = y.round();
+        if (0.0..bounds.1 as f64).contains(&y) {
+            for pixel in &mut buffer[y as usize * bounds.0..(y as usize + 1) * bounds.0] {
+                *pixel = color(im, *pixel);
+            }
+        }
+    }
+
+    for &(re, x) in &grid.vertical {
+        let origin = (label(x), label(0.0));
+        hud::draw_at(buffer, bounds, origin, &grid.label(re), scale_factor);
+    }
+    for &(im, y) in &grid.horizontal {
+        let origin = (label(0.0), label(y));
+        hud::draw_at(buffer, bounds, origin, &grid.label(im), scale_factor);
+    }
+}
+
+/// `pixel` a quarter of the way to white.
+fn lighten(pixel: u32) -> u32 {
+    let channel = |shift: u32| {
+        let value = (pixel >> shift) & 0xFF;
+        (value + (0xFF - value) / 4) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
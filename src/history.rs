@@ -0,0 +1,41 @@
+//! The cameras visited before, for going back to them after navigating into a dead end.
+use std::collections::VecDeque;
+
+use mandelbrot::export::Camera;
+
+/// How many cameras are kept to go back to, the oldest ones are forgotten beyond that.
+const CAPACITY: usize = 100;
+
+/// The undo and redo stacks of the camera.
+#[derive(Debug, Default)]
+pub struct History {
+    // The most recent camera is at the back
+    undo: VecDeque<Camera>,
+    redo: Vec<Camera>,
+}
+
+impl History {
+    /// Remember `camera` as the one before a navigation, which makes the cameras undone
+    /// so far unreachable.
+    pub fn push(&mut self, camera: Camera) {
+        if self.undo.len() == CAPACITY {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(camera);
+        self.redo.clear();
+    }
+
+    /// The camera before `current`, which can then be gone back to with [`History::redo`].
+    pub fn undo(&mut self, current: Camera) -> Option<Camera> {
+        let previous = self.undo.pop_back()?;
+        self.redo.push(current);
+        Some(previous)
+    }
+
+    /// The camera undone last, the opposite of [`History::undo`].
+    pub fn redo(&mut self, current: Camera) -> Option<Camera> {
+        let next = self.redo.pop()?;
+        self.undo.push_back(current);
+        Some(next)
+    }
+}
@@ -0,0 +1,266 @@
+//! A few lines of status text drawn over the fractal, with a tiny bitmap font for the
+//! frames rendered on the CPU, which have no text rendering otherwise.
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use mandelbrot::cpu::CountStats;
+
+/// How many of the latest frames the frame time is averaged over.
+const AVERAGED_FRAMES: usize = 30;
+
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+// Every glyph pixel is drawn as a SCALE × SCALE square at a scale factor of 1.0
+const SCALE: f64 = 2.0;
+// The distance of the text from the corner of the window, and from the edge of its
+// backdrop, in glyph pixels
+const MARGIN: usize = 4;
+const PADDING: usize = 2;
+
+/// The times of the latest frames, smoothing out the jitter of single frames.
+#[derive(Debug, Default)]
+pub struct FrameTimes {
+    // The most recent frame is at the back
+    times: VecDeque<Duration>,
+}
+
+impl FrameTimes {
+    pub fn push(&mut self, time: Duration) {
+        if self.times.len() == AVERAGED_FRAMES {
+            self.times.pop_front();
+        }
+        self.times.push_back(time);
+    }
+
+    /// The average time of the latest frames, None before the first one.
+    pub fn average(&self) -> Option<Duration> {
+        let frames = self.times.len() as u32;
+        (frames > 0).then(|| self.times.iter().sum::<Duration>() / frames)
+    }
+
+    /// The frame rate the average frame time allows for.
+    pub fn fps(&self) -> Option<f64> {
+        self.average()
+            .map(|average| 1.0 / average.as_secs_f64().max(f64::EPSILON))
+    }
+
+    /// The average frame time and frame rate, for example `12.5ms 80fps`.
+    pub fn summary(&self) -> String {
+        match (self.average(), self.fps()) {
+            (Some(average), Some(fps)) => {
+                format!("{:.1}ms {:.0}fps", average.as_secs_f64() * 1000.0, fps)
+            }
+            _ => "-".to_string(),
+        }
+    }
+}
+
+/// The status text of the view, one item per line, followed by the `cursor` point while
+/// it's in the window and by how the escape counts are distributed when they're known.
+pub fn text(
+    center_point: (f64, f64),
+    zoom: f64,
+    iterations: u32,
+    frame_times: &FrameTimes,
+    cursor: Option<(f64, f64)>,
+    stats: Option<&CountStats>,
+) -> String {
+    // Enough decimals to tell apart the centers of views a pixel apart
+    let decimals = (zoom.log10().max(0.0) as usize + 4).min(17);
+    let zoom = zoom_text(zoom);
+    let mut text = format!(
+        "re {:.*}\nim {:.*}\nzoom {}\niter {}\nframe {}",
+        decimals,
+        center_point.0,
+        decimals,
+        center_point.1,
+        zoom,
+        iterations,
+        frame_times.summary()
+    );
+    if let Some(cursor) = cursor {
+        text.push_str(&format!(
+            "\ncur {:.*} {:+.*}i",
+            decimals, cursor.0, decimals, cursor.1
+        ));
+    }
+    if let Some(stats) = stats {
+        text.push_str(&format!(
+            "\nesc {:.0}-{:.0} avg {:.1}\nin {:.1}%",
+            stats.min,
+            stats.max,
+            stats.mean,
+            100.0 * stats.interior
+        ));
+        if stats.limit_too_low() {
+            text.push_str("\nraise iter");
+        }
+    }
+    text
+}
+
+/// The window title showing what renders the view, its zoom and the frame rate, for
+/// example `Mandelbrot - GPU - zoom 2.5 - 12.5ms 80fps`.
+pub fn title(renderer: &str, zoom: f64, frame_times: &FrameTimes) -> String {
+    format!(
+        "Mandelbrot - {} - zoom {} - {}",
+        renderer,
+        zoom_text(zoom),
+        frame_times.summary()
+    )
+}
+
+// The exponent takes over once the zoom has too many digits to read at a glance
+fn zoom_text(zoom: f64) -> String {
+    if zoom < 1.0e6 {
+        format!("{:.1}", zoom)
+    } else {
+        format!("{:.3e}", zoom)
+    }
+}
+
+/// Draw `text` into the top left corner of the 0RGB `buffer` of `bounds`, white on a
+/// black backdrop, scaled up by the `scale_factor` of the window to stay readable on
+/// high DPI screens.
+pub fn draw(buffer: &mut [u32], bounds: (usize, usize), text: &str, scale_factor: f64) {
+    let margin = MARGIN * glyph_scale(scale_factor);
+    draw_at(buffer, bounds, (margin, margin), text, scale_factor);
+}
+
+/// Darken every pixel of the 0RGB `buffer` to half its brightness, so text drawn over it
+/// stands out.
+pub fn dim(buffer: &mut [u32]) {
+    for pixel in buffer {
+        *pixel = (*pixel >> 1) & 0x7F7F7F;
+    }
+}
+
+/// Draw a note that the frame is still rendering into the bottom left corner of the 0RGB
+/// `buffer` of `bounds`, in the same style as [`draw`].
+pub fn draw_busy(buffer: &mut [u32], bounds: (usize, usize), scale_factor: f64) {
+    let scale = glyph_scale(scale_factor);
+    let height = (GLYPH_HEIGHT + 2 + 2 * PADDING) * scale;
+    let margin = MARGIN * scale;
+    let origin = (margin, bounds.1.saturating_sub(margin + height));
+    draw_at(buffer, bounds, origin, "rendering...", scale_factor);
+}
+
+/// Draw `text` like [`draw`] does, with the top left corner of its backdrop at `origin`.
+pub fn draw_at(
+    buffer: &mut [u32],
+    bounds: (usize, usize),
+    origin: (usize, usize),
+    text: &str,
+    scale_factor: f64,
+) {
+    let scale = glyph_scale(scale_factor);
+    let advance = (GLYPH_WIDTH + 1) * scale;
+    let line_height = (GLYPH_HEIGHT + 2) * scale;
+    let padding = PADDING * scale;
+    let columns = text
+        .lines()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    let rows = text.lines().count();
+    let backdrop = (
+        origin.0 + columns * advance + 2 * padding,
+        origin.1 + rows * line_height + 2 * padding,
+    );
+    for y in origin.1..backdrop.1.min(bounds.1) {
+        for x in origin.0..backdrop.0.min(bounds.0) {
+            buffer[y * bounds.0 + x] = 0x000000;
+        }
+    }
+
+    for (row, line) in text.lines().enumerate() {
+        for (column, character) in line.chars().enumerate() {
+            let origin = (
+                origin.0 + padding + column * advance,
+                origin.1 + padding + row * line_height,
+            );
+            draw_glyph(buffer, bounds, origin, scale, glyph(character));
+        }
+    }
+}
+
+/// The size of a glyph pixel in physical pixels at `scale_factor`.
+fn glyph_scale(scale_factor: f64) -> usize {
+    (SCALE * scale_factor).round().max(1.0) as usize
+}
+
+fn draw_glyph(
+    buffer: &mut [u32],
+    bounds: (usize, usize),
+    origin: (usize, usize),
+    scale: usize,
+    glyph: [&str; GLYPH_HEIGHT],
+) {
+    for (glyph_y, line) in glyph.iter().enumerate() {
+        for (glyph_x, _) in line.char_indices().filter(|&(_, pixel)| pixel == '#') {
+            for y in 0..scale {
+                for x in 0..scale {
+                    let (x, y) = (
+                        origin.0 + glyph_x * scale + x,
+                        origin.1 + glyph_y * scale + y,
+                    );
+                    if x < bounds.0 && y < bounds.1 {
+                        buffer[y * bounds.0 + x] = 0xFFFFFF;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The 3 × 5 bitmap of `character`, the letters all in lowercase, blank for the ones the
+/// text never contains.
+fn glyph(character: char) -> [&'static str; GLYPH_HEIGHT] {
+    match character.to_ascii_lowercase() {
+        '0' => ["###", "#.#", "#.#", "#.#", "###"],
+        '1' => [".#.", "##.", ".#.", ".#.", "###"],
+        '2' => ["###", "..#", "###", "#..", "###"],
+        '3' => ["###", "..#", "###", "..#", "###"],
+        '4' => ["#.#", "#.#", "###", "..#", "..#"],
+        '5' => ["###", "#..", "###", "..#", "###"],
+        '6' => ["###", "#..", "###", "#.#", "###"],
+        '7' => ["###", "..#", "..#", "..#", "..#"],
+        '8' => ["###", "#.#", "###", "#.#", "###"],
+        '9' => ["###", "#.#", "###", "..#", "###"],
+        '.' => ["...", "...", "...", "...", ".#."],
+        '%' => ["#.#", "..#", ".#.", "#..", "#.#"],
+        '-' => ["...", "...", "###", "...", "..."],
+        ',' => ["...", "...", "...", ".#.", "#.."],
+        '+' => ["...", ".#.", "###", ".#.", "..."],
+        '?' => ["##.", "..#", ".#.", "...", ".#."],
+        '[' => ["##.", "#..", "#..", "#..", "##."],
+        ']' => [".##", "..#", "..#", "..#", ".##"],
+        'a' => ["...", ".##", "#.#", "#.#", ".##"],
+        'b' => ["#..", "#..", "##.", "#.#", "##."],
+        'c' => ["...", ".##", "#..", "#..", ".##"],
+        'd' => ["..#", "..#", ".##", "#.#", ".##"],
+        'e' => ["...", ".#.", "###", "#..", ".##"],
+        'f' => [".##", "#..", "###", "#..", "#.."],
+        'g' => [".##", "#.#", ".##", "..#", "##."],
+        'h' => ["#..", "#..", "##.", "#.#", "#.#"],
+        'i' => [".#.", "...", ".#.", ".#.", ".#."],
+        'j' => ["..#", "...", "..#", "#.#", ".#."],
+        'k' => ["#..", "#.#", "##.", "#.#", "#.#"],
+        'l' => ["##.", ".#.", ".#.", ".#.", "###"],
+        'm' => ["...", "###", "###", "#.#", "#.#"],
+        'n' => ["...", "##.", "#.#", "#.#", "#.#"],
+        'o' => ["...", ".#.", "#.#", "#.#", ".#."],
+        'p' => ["...", "##.", "#.#", "##.", "#.."],
+        'q' => ["...", ".##", "#.#", ".##", "..#"],
+        'r' => ["...", ".##", "#..", "#..", "#.."],
+        's' => ["...", ".##", "##.", "..#", "##."],
+        't' => [".#.", "###", ".#.", ".#.", ".##"],
+        'u' => ["...", "#.#", "#.#", "#.#", ".##"],
+        'v' => ["...", "#.#", "#.#", "#.#", ".#."],
+        'w' => ["...", "#.#", "#.#", "###", "###"],
+        'x' => ["...", "#.#", ".#.", ".#.", "#.#"],
+        'y' => ["...", "#.#", ".##", "..#", "##."],
+        'z' => ["...", "###", ".#.", "#..", "###"],
+        _ => ["...", "...", "...", "...", "..."],
+    }
+}
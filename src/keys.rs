@@ -0,0 +1,221 @@
+//! The keybindings, listed in one table that both handles the keys and makes up the
+//! help shown with F1, so the help stays in sync with them.
+use winit::keyboard::{Key, ModifiersState, NamedKey};
+
+/// What a binding does, carried out by `InnerApp::key_pressed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    TogglePause,
+    ToggleHelp,
+    ResetAll,
+    UndoCamera,
+    RedoCamera,
+    ClearMeasurement,
+    /// Save the view into the slot of the digit pressed.
+    SaveBookmark,
+    JumpToBookmark,
+    CopyCoordinates,
+    Paste,
+    ResetCamera,
+    ResetCameraInstantly,
+    SaveCounts,
+    SavePng,
+    SaveHighResolutionPng,
+    ExportZoomGif,
+    ToggleSettingsPanel,
+    NextEscapeNorm,
+    HalveIterations,
+    DoubleIterations,
+    ToggleAdaptiveIterations,
+    NextFractalKind,
+    ToggleJulia,
+    NextOrbitTrap,
+    LowerPower,
+    RaisePower,
+    PrintState,
+    PrintCountStats,
+    ReloadPalette,
+    ToggleEqualize,
+    ToggleFillInterior,
+    ToggleInteriorPeriods,
+    ToggleColorCycling,
+    ToggleGrid,
+    NextGamma,
+    NextColorMapping,
+    ToggleYAxis,
+    TypeCoordinates,
+    ToggleDither,
+    ToggleTwoUp,
+    NextSupersampling,
+    SaveRenderConfig,
+    CopyView,
+    ToggleHud,
+    ToggleMeasuring,
+    ToggleMinimap,
+    NextInitialZ,
+}
+
+/// The key a binding reacts to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    Named(NamedKey),
+    /// A character, compared in lowercase.
+    Character(&'static str),
+    /// Any of the digits from 1 to 9.
+    Digit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+    pub trigger: Trigger,
+    /// The modifiers that have to be held, others being ignored.
+    pub control: bool,
+    pub shift: bool,
+    pub action: Action,
+    pub description: &'static str,
+}
+
+// The modifiers bindings need held
+const NONE: (bool, bool) = (false, false);
+const SHIFT: (bool, bool) = (false, true);
+const CONTROL: (bool, bool) = (true, false);
+const CONTROL_SHIFT: (bool, bool) = (true, true);
+
+const fn binding(
+    trigger: Trigger,
+    (control, shift): (bool, bool),
+    action: Action,
+    description: &'static str,
+) -> Binding {
+    Binding {
+        trigger,
+        control,
+        shift,
+        action,
+        description,
+    }
+}
+
+const fn named(key: NamedKey) -> Trigger {
+    Trigger::Named(key)
+}
+
+const fn character(character: &'static str) -> Trigger {
+    Trigger::Character(character)
+}
+
+/// Every keybinding, the first one matching a key press winning, so the ones needing
+/// modifiers come before the same key without them.
+#[rustfmt::skip]
+pub const BINDINGS: &[Binding] = &[
+    binding(named(NamedKey::F1), NONE, Action::ToggleHelp, "Show or hide this help"),
+    binding(character("?"), NONE, Action::ToggleHelp, "Show or hide this help"),
+    binding(named(NamedKey::Space), NONE, Action::TogglePause, "Pause or continue"),
+    binding(named(NamedKey::Delete), NONE, Action::ResetAll, "Reset every setting"),
+    binding(named(NamedKey::Backspace), NONE, Action::UndoCamera, "Go back a view"),
+    binding(named(NamedKey::Escape), NONE, Action::ClearMeasurement, "Clear the measurement"),
+    binding(Trigger::Digit, CONTROL, Action::SaveBookmark, "Save the view as a bookmark"),
+    binding(Trigger::Digit, NONE, Action::JumpToBookmark, "Go to a bookmark"),
+    binding(character("z"), CONTROL_SHIFT, Action::RedoCamera, "Go forward a view"),
+    binding(character("z"), CONTROL, Action::UndoCamera, "Go back a view"),
+    binding(character("y"), CONTROL, Action::RedoCamera, "Go forward a view"),
+    binding(character("c"), CONTROL, Action::CopyCoordinates, "Copy the coordinates"),
+    binding(character("v"), CONTROL, Action::Paste, "Paste coordinates or a view"),
+    binding(character("r"), SHIFT, Action::ResetCameraInstantly, "Frame the whole fractal at once"),
+    binding(character("r"), NONE, Action::ResetCamera, "Frame the whole fractal"),
+    binding(character("s"), SHIFT, Action::SaveCounts, "Save the escape counts"),
+    binding(character("s"), NONE, Action::SavePng, "Save a PNG"),
+    binding(character("h"), NONE, Action::SaveHighResolutionPng, "Save a high resolution PNG"),
+    binding(character("v"), NONE, Action::ExportZoomGif, "Export a zoom GIF"),
+    binding(character("u"), NONE, Action::ToggleSettingsPanel, "Show or hide the settings"),
+    binding(character("n"), NONE, Action::NextEscapeNorm, "Next escape norm"),
+    binding(character("["), NONE, Action::HalveIterations, "Halve the iterations"),
+    binding(character("]"), NONE, Action::DoubleIterations, "Double the iterations"),
+    binding(character("a"), NONE, Action::ToggleAdaptiveIterations, "Adaptive iterations"),
+    binding(character("b"), NONE, Action::NextFractalKind, "Next fractal"),
+    binding(character("j"), NONE, Action::ToggleJulia, "Julia set of the center"),
+    binding(character("t"), NONE, Action::NextOrbitTrap, "Next orbit trap"),
+    binding(character(","), NONE, Action::LowerPower, "Lower the power"),
+    binding(character("."), NONE, Action::RaisePower, "Raise the power"),
+    binding(character("p"), SHIFT, Action::PrintState, "Print the view state"),
+    binding(character("i"), SHIFT, Action::PrintCountStats, "Print the escape count statistics"),
+    binding(character("p"), NONE, Action::ReloadPalette, "Reload the palette"),
+    binding(character("e"), NONE, Action::ToggleEqualize, "Histogram equalization"),
+    binding(character("f"), SHIFT, Action::ToggleInteriorPeriods, "Color the interior by period"),
+    binding(character("f"), NONE, Action::ToggleFillInterior, "Fill the interior"),
+    binding(character("l"), NONE, Action::ToggleColorCycling, "Color cycling"),
+    binding(character("g"), SHIFT, Action::ToggleGrid, "Show or hide the grid"),
+    binding(character("g"), NONE, Action::NextGamma, "Next gamma"),
+    binding(character("w"), NONE, Action::NextColorMapping, "Next color mapping"),
+    binding(character("y"), NONE, Action::ToggleYAxis, "Flip the imaginary axis"),
+    binding(character("k"), NONE, Action::TypeCoordinates, "Type coordinates in the terminal"),
+    binding(character("d"), SHIFT, Action::ToggleDither, "Dithering"),
+    binding(character("d"), NONE, Action::ToggleTwoUp, "Compare with the CPU side by side"),
+    binding(character("x"), NONE, Action::NextSupersampling, "Next supersampling"),
+    binding(character("c"), SHIFT, Action::SaveRenderConfig, "Save the render configuration"),
+    binding(character("c"), NONE, Action::CopyView, "Copy the view"),
+    binding(character("o"), NONE, Action::ToggleHud, "Show or hide the status"),
+    binding(character("m"), SHIFT, Action::ToggleMeasuring, "Measure distances"),
+    binding(character("m"), NONE, Action::ToggleMinimap, "Show or hide the minimap"),
+    binding(character("i"), NONE, Action::NextInitialZ, "Next initial z"),
+];
+
+impl Binding {
+    fn matches(&self, key: &Key<&str>, modifiers: ModifiersState) -> bool {
+        if (self.control && !modifiers.control_key()) || (self.shift && !modifiers.shift_key()) {
+            return false;
+        }
+        match (self.trigger, key) {
+            (Trigger::Named(named), Key::Named(pressed)) => named == *pressed,
+            (Trigger::Character(character), Key::Character(pressed)) => {
+                character == pressed.to_lowercase()
+            }
+            (Trigger::Digit, Key::Character(pressed)) => digit(pressed).is_some(),
+            _ => false,
+        }
+    }
+
+    /// How the key is written in the help, for example `Ctrl+Shift+Z`.
+    fn keys(&self) -> String {
+        let mut keys = String::new();
+        if self.control {
+            keys.push_str("Ctrl+");
+        }
+        if self.shift {
+            keys.push_str("Shift+");
+        }
+        match self.trigger {
+            Trigger::Named(named) => keys.push_str(&format!("{:?}", named)),
+            Trigger::Character(character) => keys.push_str(&character.to_uppercase()),
+            Trigger::Digit => keys.push_str("1-9"),
+        }
+        keys
+    }
+}
+
+/// The action bound to `key` pressed along with `modifiers`, if any.
+pub fn action(key: &Key<&str>, modifiers: ModifiersState) -> Option<Action> {
+    BINDINGS
+        .iter()
+        .find(|binding| binding.matches(key, modifiers))
+        .map(|binding| binding.action)
+}
+
+/// The digit from 1 to 9 of a key press, the bookmark slots.
+pub fn digit(character: &str) -> Option<u8> {
+    match character.parse() {
+        Ok(digit @ 1..=9) => Some(digit),
+        _ => None,
+    }
+}
+
+/// Every binding on a line of its own, its keys in a column followed by what it does.
+pub fn help() -> String {
+    let keys: Vec<String> = BINDINGS.iter().map(Binding::keys).collect();
+    let width = keys.iter().map(|keys| keys.len()).max().unwrap_or(0);
+    keys.iter()
+        .zip(BINDINGS)
+        .map(|(keys, binding)| format!("{:<width$}  {}", keys, binding.description))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,232 @@
+//! Rendering the Mandelbrot set and its relatives, on the CPU into images and animations,
+//! and on the GPU into a window.
+//!
+//! The `mandelbrot` binary is an interactive viewer built on top of this.
+use serde::{Deserialize, Serialize};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+pub mod bookmarks;
+pub mod cpu;
+pub mod error;
+pub mod export;
+pub mod gpu;
+pub mod palette;
+pub mod render_config;
+pub mod share;
+
+use cpu::FractalKind;
+
+/// The height of the part of the complex plane in view at a zoom of 1.0, which holds the
+/// whole Mandelbrot set with a bit of room to spare.
+pub const VIEW_HEIGHT: f64 = 2.6;
+pub const DEFAULT_CENTER_POINT: (f64, f64) = (-0.5, 0.0);
+pub const DEFAULT_ZOOM: f64 = 1.0;
+pub const BURNING_SHIP_CENTER_POINT: (f64, f64) = (-0.4, -0.5);
+pub const BURNING_SHIP_HEIGHT: f64 = 3.25;
+/// Multibrots are roughly round and centered on the origin.
+pub const MULTIBROT_CENTER_POINT: (f64, f64) = (0.0, 0.0);
+pub const MULTIBROT_HEIGHT: f64 = 2.9;
+/// Julia sets of points in the Mandelbrot set stay within the circle of radius 2, the
+/// interesting ones well within the box from -1.5 to 1.5 on both axes.
+pub const JULIA_CENTER_POINT: (f64, f64) = (0.0, 0.0);
+pub const JULIA_HEIGHT: f64 = 3.0;
+/// How many iterations the adaptive iteration limit adds for every doubling of the zoom.
+pub const ADAPTIVE_ITERATIONS_PER_DOUBLING: f64 = 64.0;
+/// The adaptive iteration limit stops growing here, so deep zooms don't slow down to
+/// multi-second frames. A higher limit set by hand is still respected.
+pub const ADAPTIVE_ITERATION_CAP: u32 = 4096;
+/// The iteration limits reachable with the keybindings and the settings panel, and
+/// accepted from views shared with others.
+pub const ITERATION_LIMITS: std::ops::RangeInclusive<u32> = 16..=65536;
+/// The squared bailout radii selectable in the settings panel.
+pub const BAILOUT_SQUARED_LIMITS: std::ops::RangeInclusive<f64> =
+    cpu::DEFAULT_BAILOUT_SQUARED..=1.0e6;
+/// The powers of the Multibrots reachable with the keybindings and the settings panel.
+pub const POWER_LIMITS: std::ops::RangeInclusive<u32> = 2..=8;
+
+/// Everything deciding what a render of the fractal looks like.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderParams {
+    pub camera: export::Camera,
+    /// The size of the render in pixels.
+    pub resolution: (usize, usize),
+    pub iteration: cpu::Iteration,
+    pub style: export::Style,
+    /// The number of threads to render on, 0 for all of them.
+    pub threads: usize,
+}
+
+/// Render `params` on the CPU into RGB pixels.
+pub fn render(params: &RenderParams) -> Vec<u8> {
+    let mut counts = cpu::EscapeCounts::default();
+    export::render_view(
+        &mut counts,
+        params.resolution,
+        params.camera,
+        params.iteration,
+        &params.style,
+        cpu::thread_count(params.threads),
+    )
+}
+
+/// Render `params` on the CPU into the caller's 0RGB pixels `out`, row by row, the
+/// layout softbuffer and most windowing toolkits take, for embedding the renderer.
+///
+/// # Panics
+///
+/// When `out` doesn't have exactly as many pixels as `params.resolution`.
+pub fn render_frame(params: &RenderParams, out: &mut [u32]) {
+    assert_eq!(out.len(), params.resolution.0 * params.resolution.1);
+    pack_0rgb(&render(params), out);
+}
+
+/// Pack the RGB `pixels` into the 0RGB pixels of `out`, one after the other.
+pub fn pack_0rgb(pixels: &[u8], out: &mut [u32]) {
+    for (target, pixel) in out.iter_mut().zip(pixels.chunks_exact(3)) {
+        *target = ((pixel[0] as u32) << 16) | ((pixel[1] as u32) << 8) | pixel[2] as u32;
+    }
+}
+
+/// The iteration limit for rendering a view at `zoom`, `max_iterations` unless it's
+/// `adaptive`, in which case it grows logarithmically with the zoom, up to
+/// [`ADAPTIVE_ITERATION_CAP`].
+pub fn iteration_limit(max_iterations: u32, adaptive: bool, zoom: f64) -> u32 {
+    if !adaptive {
+        return max_iterations;
+    }
+    let growth = (ADAPTIVE_ITERATIONS_PER_DOUBLING * zoom.log2().max(0.0)) as u32;
+    let cap = ADAPTIVE_ITERATION_CAP.max(max_iterations);
+    max_iterations.saturating_add(growth).min(cap)
+}
+
+/// The center point and the height of the part of the complex plane holding the whole of
+/// the `kind` fractal raising `z` to `power`, or of its Julia sets if `julia`.
+pub fn whole_fractal(kind: FractalKind, power: u32, julia: bool) -> ((f64, f64), f64) {
+    match kind {
+        _ if julia => (JULIA_CENTER_POINT, JULIA_HEIGHT),
+        FractalKind::Mandelbrot if power > 2 => (MULTIBROT_CENTER_POINT, MULTIBROT_HEIGHT),
+        FractalKind::Mandelbrot => (DEFAULT_CENTER_POINT, VIEW_HEIGHT / DEFAULT_ZOOM),
+        FractalKind::BurningShip => (BURNING_SHIP_CENTER_POINT, BURNING_SHIP_HEIGHT),
+    }
+}
+
+/// The center point and zoom framing the whole of the fractal, as [`whole_fractal`]
+/// places it, so it fits the window vertically.
+pub fn default_camera_settings(kind: FractalKind, power: u32, julia: bool) -> ((f64, f64), f64) {
+    let (center_point, height) = whole_fractal(kind, power, julia);
+    (center_point, VIEW_HEIGHT / height)
+}
+
+/// Which way the imaginary axis points in the window and in the images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum YAxis {
+    /// The mathematical convention, the imaginary part growing up the window.
+    #[default]
+    Up,
+    /// The convention of the screen, the imaginary part growing down the window along
+    /// with the rows of pixels.
+    Down,
+}
+
+impl YAxis {
+    pub fn flipped(self) -> Self {
+        match self {
+            YAxis::Up => YAxis::Down,
+            YAxis::Down => YAxis::Up,
+        }
+    }
+}
+
+/// Compute the top left corner of the view and the width and height of the visible
+/// region of the complex plane, for a view centered on `center_point`.
+///
+/// The height is negative when `y_axis` points down, so going down from the top left
+/// corner by the height still ends up in the bottom left one, and every conversion
+/// between pixels and points built on this flips along with it.
+pub fn center_to_start_conditions(
+    center_point: (f64, f64),
+    zoom: f64,
+    window_resolution: PhysicalSize<u32>,
+    y_axis: YAxis,
+) -> ((f64, f64), (f64, f64)) {
+    // At 1.0 zoom the whole Mandelbrot set fits the window vertically, the other
+    // fractals are framed by zooming, so the zoom means the same whatever is rendered
+    let scale = (VIEW_HEIGHT / window_resolution.height as f64) * (1.0 / zoom);
+    let width = window_resolution.width as f64 * scale;
+    let height = match y_axis {
+        YAxis::Up => window_resolution.height as f64 * scale,
+        YAxis::Down => -(window_resolution.height as f64 * scale),
+    };
+    let top_left = (
+        center_point.0 - (width / 2.0),
+        center_point.1 + (height / 2.0),
+    );
+    (top_left, (width, height))
+}
+
+/// True when the view centered on `center_point` at `zoom` in a window of
+/// `window_resolution`, which mustn't be zero sized, can be rendered: its corner finite
+/// and its size not shrunk into the denormals, or to nothing, by a zoom too deep for f64.
+pub fn view_is_valid(
+    center_point: (f64, f64),
+    zoom: f64,
+    window_resolution: PhysicalSize<u32>,
+) -> bool {
+    let (top_left, (width, height)) =
+        center_to_start_conditions(center_point, zoom, window_resolution, YAxis::Up);
+    zoom.is_finite()
+        && zoom > 0.0
+        && top_left.0.is_finite()
+        && top_left.1.is_finite()
+        && width.is_normal()
+        && height.is_normal()
+}
+
+/// The point of the complex plane under `pixel`, in a view centered on `center_point`.
+pub fn pixel_to_point(
+    center_point: (f64, f64),
+    zoom: f64,
+    window_resolution: PhysicalSize<u32>,
+    y_axis: YAxis,
+    pixel: PhysicalPosition<f64>,
+) -> (f64, f64) {
+    let (top_left, (width, height)) =
+        center_to_start_conditions(center_point, zoom, window_resolution, y_axis);
+    (
+        top_left.0 + pixel.x * width / window_resolution.width as f64,
+        top_left.1 - pixel.y * height / window_resolution.height as f64,
+    )
+}
+
+/// The pixel `point` of the complex plane is under in a view centered on `center_point`,
+/// the inverse of [`pixel_to_point`]. Points outside of the view give pixels outside of
+/// `window_resolution`.
+pub fn point_to_pixel(
+    center_point: (f64, f64),
+    zoom: f64,
+    window_resolution: PhysicalSize<u32>,
+    y_axis: YAxis,
+    point: (f64, f64),
+) -> PhysicalPosition<f64> {
+    let (top_left, (width, height)) =
+        center_to_start_conditions(center_point, zoom, window_resolution, y_axis);
+    PhysicalPosition::new(
+        (point.0 - top_left.0) * window_resolution.width as f64 / width,
+        (top_left.1 - point.1) * window_resolution.height as f64 / height,
+    )
+}
+
+/// True once neighbouring pixels of the view are closer together than what f32 can tell
+/// apart around the view's coordinates, so zooming in further only magnifies rounding errors.
+pub fn precision_exhausted(
+    center_point: (f64, f64),
+    zoom: f64,
+    window_resolution: PhysicalSize<u32>,
+) -> bool {
+    let (_, (width, height)) =
+        center_to_start_conditions(center_point, zoom, window_resolution, YAxis::Up);
+    let pixel_step = width / window_resolution.width as f64;
+    let magnitude = (center_point.0.abs() + width / 2.0).max(center_point.1.abs() + height / 2.0);
+    pixel_step < magnitude * f32::EPSILON as f64
+}
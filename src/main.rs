@@ -1,53 +1,2389 @@
-use std::{borrow::Cow, sync::Arc};
-
-use wgpu::{
-    BindGroup, BindGroupEntry, BufferBinding, BufferUsages, Device, Queue, RenderPipeline, Surface,
+use std::{
+    collections::BTreeMap,
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    time::{Duration, Instant},
 };
+
+use num::Complex;
 use winit::{
     application::ApplicationHandler,
-    event::{DeviceEvent, ElementState, WindowEvent},
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{DeviceEvent, ElementState, KeyEvent, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    window::Window,
+    keyboard::{Key, ModifiersState},
+    window::{CursorIcon, Window},
+};
+
+mod bench;
+mod config;
+mod grid;
+mod history;
+mod hud;
+mod keys;
+mod measure;
+mod minimap;
+mod session;
+mod ui;
+
+use config::{Backend, Config};
+use history::History;
+use keys::Action;
+use mandelbrot::bookmarks::{Bookmark, Bookmarks};
+use mandelbrot::cpu::{
+    self, ColorMapping, EscapeNorm, FractalKind, InitialZ, TrapShape, DEFAULT_BAILOUT_SQUARED,
+    DEFAULT_POWER, DEFAULT_TRAP_RADIUS,
 };
+use mandelbrot::error::Error;
+use mandelbrot::export;
+use mandelbrot::gpu::{Scene, Wgpu};
+use mandelbrot::palette::Palette;
+use mandelbrot::render_config::RenderConfig;
+use mandelbrot::share::{Coordinates, SharedView};
+use mandelbrot::{center_to_start_conditions, default_camera_settings, pixel_to_point};
+use mandelbrot::{iteration_limit, precision_exhausted, view_is_valid, RenderParams, YAxis};
+use mandelbrot::{BAILOUT_SQUARED_LIMITS, ITERATION_LIMITS, POWER_LIMITS};
+use session::Session;
 
-mod cpu;
+/// Box zoom selections narrower or shorter than this many pixels are ignored.
+const MIN_SELECTION: f64 = 4.0;
+/// A press and release of the left button with the cursor staying within this many
+/// pixels of where it was pressed is a click rather than a drag.
+const CLICK_DISTANCE: f64 = 4.0;
+/// The largest supersampling factor, the cost of a frame grows with its square.
+const MAX_SUPERSAMPLING: u32 = 3;
+/// The resolution of the wallpaper sized PNG export.
+const HIGH_RESOLUTION_EXPORT: PhysicalSize<u32> = PhysicalSize::new(7680, 4320);
+/// How many seconds it takes an animated camera change to get about two thirds of the
+/// way to its target.
+const CAMERA_SMOOTHING: f64 = 0.08;
+/// The stride between the pixels rendered by the first, coarsest pass of a frame
+/// rendered on the CPU, halved by every following pass until the frame is complete.
+const COARSE_STRIDE: usize = 4;
+/// The gammas stepped through with the G key.
+const GAMMAS: [f32; 4] = [1.0, 0.7, 0.5, 0.4];
+/// The gammas selectable in the settings panel.
+const GAMMA_LIMITS: std::ops::RangeInclusive<f32> = 0.2..=3.0;
+/// How much of the palette the colors cycle through every second.
+const COLOR_CYCLE_SPEED: f32 = 0.1;
+/// How often the live status in the window title changes at most, to stay readable.
+const TITLE_UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+/// How many seconds it takes the view gliding on after a flick to slow down to about a
+/// third of its speed.
+const GLIDE_DECAY: f64 = 0.25;
+/// How many seconds the speed of a drag is averaged over, smoothing out the jitter of
+/// the single mouse movements.
+const DRAG_SPEED_SMOOTHING: f64 = 0.03;
+/// A drag released after the mouse rested for this long stops where it is.
+const FLICK_TIMEOUT: Duration = Duration::from_millis(50);
+/// The speed in pixels per second a drag has to be released at to glide on, and the
+/// speed the glide stops at.
+const MIN_FLICK_SPEED: f64 = 300.0;
+const MIN_GLIDE_SPEED: f64 = 10.0;
+const PRECISION_WARNING: &str =
+    "Precision limit of the GPU reached, press S to save a sharper f64 render on the CPU";
 
 #[derive(Default)]
 struct App {
     app: Option<InnerApp>,
+    // The settings from the config file, overridden by the command line
+    config: Config,
+    // The view given on the command line to start from
+    view: Option<SharedView>,
+    // The render configuration given on the command line, rendered as it is
+    render_config: Option<RenderConfig>,
+    // The iteration settings of the last session, its camera and iteration limit being
+    // part of `config` already
+    session: Option<Bookmark>,
+}
+
+/// The last, full resolution pass of a frame rendered on the CPU, on a thread of its own
+/// so the input arriving in the meantime is still handled, and can abort it.
+struct CpuRender {
+    abort: Arc<AtomicBool>,
+    // The tile cache and the escape counts the pass renders into, sent back once it's
+    // done or aborted, along with whether it's done
+    receiver: mpsc::Receiver<(cpu::TileCache, cpu::EscapeCounts, bool)>,
+}
+
+/// The settings every fractal kind keeps of its own, see [`config::KindConfig`].
+struct KindSettings {
+    max_iterations: u32,
+    color_mapping: ColorMapping,
+    palette: Palette,
+    palette_path: Option<PathBuf>,
 }
 
 struct InnerApp {
     pub window: Arc<Window>,
-    pub gpu: Wgpu,
+    // What was asked to render with, saved along with the session
+    pub backend: Backend,
+    // None when no GPU is available, in which case the CPU renders into `cpu_surface`
+    pub gpu: Option<Wgpu>,
+    // The settings panel is drawn by the GPU, so it's only there along with it
+    pub ui: Option<ui::Ui>,
+    pub cpu_surface: Option<softbuffer::Surface<Arc<Window>, Arc<Window>>>,
+    // The escape counts of the last frame rendered on the CPU
+    pub counts: cpu::EscapeCounts,
+    // How those counts are distributed, None before the first frame rendered on the CPU
+    pub count_stats: Option<cpu::CountStats>,
+    pub tile_cache: cpu::TileCache,
+    // The full resolution pass in progress, which has the counts and the tile cache
+    // until it's done
+    cpu_render: Option<CpuRender>,
+    // The RGB pixels of the last frame rendered on the CPU and their bounds, shown again
+    // while paused
+    pub frame: Vec<u8>,
+    pub frame_bounds: (usize, usize),
+    // The style the complete frame was colored in, so redrawing only the overlays, as
+    // moving the cursor does, doesn't color it again
+    pub frame_style: Option<export::Style>,
+    // What the escape counts of the last frame rendered on the CPU show, with the
+    // supersampling, and the stride between its rendered pixels, 1 once it's complete
+    pub rendered_view: Option<(export::Camera, (usize, usize), cpu::Iteration, u32)>,
+    pub stride: usize,
+    // Stop rendering new frames and ignore navigation, toggled with Space
+    pub paused: bool,
 
     pub focused: bool,
     pub in_window: bool,
     pub left_mouse: ElementState,
+    pub modifiers: ModifiersState,
+    // The last known position of the cursor inside the window
+    pub cursor_position: Option<PhysicalPosition<f64>>,
+    // Where the box zoom selection in progress started, Ctrl dragging with the left button
+    pub selection_start: Option<PhysicalPosition<f64>>,
+    // The camera before the drag in progress is already in the history
+    pub drag_remembered: bool,
+    // The smoothed speed of the drag in pixels per second, and when the mouse last moved
+    pub drag_speed: (f64, f64),
+    pub drag_step: Instant,
+    // The speed the view glides on with after a flick, None when it's still
+    pub glide: Option<(f64, f64)>,
+    pub glide_step: Instant,
+    // Where the left button was pressed, until the cursor moves far enough from there
+    // for it to be a drag
+    pub click_start: Option<PhysicalPosition<f64>>,
     // The x, y coordinates of the screen center
-    pub center_point: (f32, f32),
-    pub zoom: f32,
+    pub center_point: (f64, f64),
+    pub zoom: f64,
+    // The camera an animated zoom or reset is heading for, None when not animating
+    pub target_camera: Option<export::Camera>,
+    // When the animation last moved the camera
+    pub animation_step: Instant,
+    // The last camera the view could be rendered with, the default one before the first
+    // frame, which navigation ending up with a broken view goes back to
+    pub valid_camera: export::Camera,
+    pub fractal_kind: FractalKind,
+    pub escape_norm: EscapeNorm,
+    pub initial_z: InitialZ,
+    pub max_iterations: u32,
+    // Grow the iteration limit from `max_iterations` as the view zooms in
+    pub adaptive_iterations: bool,
+    pub bailout_squared: f64,
+    // The constant of the Julia set shown instead of the Mandelbrot set
+    pub julia_c: Option<Complex<f32>>,
+    // The power z is raised to, 2 for the classic fractals and higher for Multibrots
+    pub power: u32,
+    // Color by the distance of the orbits to this shape instead of the escape counts
+    pub orbit_trap: Option<TrapShape>,
+    pub trap_center: (f64, f64),
+    pub trap_radius: f64,
+    pub palette: Palette,
+    // The file `palette` was loaded from, reloaded with the P key
+    pub palette_path: Option<PathBuf>,
+    // Histogram equalize the colors of the frames rendered on the CPU
+    pub equalize: bool,
+    // Skip iterating the inside of rectangles bordered by the set on the CPU
+    pub fill_interior: bool,
+    // Color the interior by the periods of its orbits on the CPU, toggled with Shift+F
+    pub interior_periods: bool,
+    // Stop iterating the orbits caught in a cycle on the CPU
+    pub periodicity_checking: bool,
+    // Keep shifting the colors through the palette, toggled with the L key
+    pub color_cycling: bool,
+    // How far the colors are cycled, from 0.0 to 1.0, and when it last changed
+    pub palette_phase: f32,
+    pub color_cycle_step: Instant,
+    // The counts scaled to 0.0 to 1.0 are raised to this power before looking up their
+    // colors
+    pub gamma: f32,
+    // How the counts are spread over the palette, cycled with the W key
+    pub color_mapping: ColorMapping,
+    // Dither the colors against banding, toggled with Shift+D
+    pub dither: bool,
+    // Which way the imaginary axis points, toggled with the Y key
+    pub y_axis: YAxis,
+    // Every pixel averages supersampling × supersampling samples
+    pub supersampling: u32,
+    // Show the status text with the coordinates, zoom, iterations and frame time
+    pub hud: bool,
+    // Show where the view is in an overview of the whole set, toggled with the M key
+    pub show_minimap: bool,
+    pub minimap: minimap::Minimap,
+    // Clicks place the endpoints of `measurement` instead of centering the view, toggled
+    // with Shift+M
+    pub measuring: bool,
+    pub measurement: measure::Measurement,
+    // Show the axes and a labeled grid of the complex plane, toggled with Shift+G
+    pub show_grid: bool,
+    // Show every keybinding over the dimmed fractal, toggled with F1
+    pub show_help: bool,
+    // Render the left half of the window on the CPU, next to the right half rendered on
+    // the GPU, for comparing the two, toggled with the D key
+    pub two_up: bool,
+    // What the CPU half shows and its RGB pixels, rendered again once that changes
+    pub cpu_half: Option<(RenderParams, Vec<u8>)>,
+    // How long the latest redraws took
+    pub frame_times: hud::FrameTimes,
+    // When the frame time was last shown in the window title
+    pub title_updated: Instant,
+    // The time animations leave between the starts of their frames at least, None to
+    // redraw them as fast as possible, and when the last frame started
+    pub frame_interval: Option<Duration>,
+    pub frame_start: Instant,
+    // Keep the status in the window title up to date, rather than only the name
+    pub live_title: bool,
+    // Print the size and time of every frame
+    pub verbose: bool,
+    // The number of threads the CPU renders on
+    pub threads: usize,
+    // How much scrolling by a line adds to the zoom, and how many logical pixels of a
+    // scroll by pixels make a line
+    pub zoom_per_line: f64,
+    pub pixels_per_line: f64,
+    // The zoom scrolling and box zooming stay above and at most at respectively
+    pub zoom_limits: (f64, f64),
+    // The settings the application started with, which resetting every setting goes
+    // back to
+    pub startup: Config,
+    // The settings last used with the other fractal kinds, taken again when switching
+    // back to them
+    pub kind_settings: BTreeMap<FractalKind, KindSettings>,
+    // Saved with Ctrl and a number key, jumped to with the number key
+    pub bookmarks: Bookmarks,
+    // The cameras to go back to with Ctrl+Z or Backspace, and forward again with Ctrl+Y
+    pub history: History,
+    // The line typed into the terminal after pressing K, while it's being waited for
+    pub typed_coordinates: Option<mpsc::Receiver<String>>,
 }
 
 impl InnerApp {
-    pub fn new(event_loop: &winit::event_loop::ActiveEventLoop) -> Self {
+    pub fn new(
+        event_loop: &winit::event_loop::ActiveEventLoop,
+        config: Config,
+        view: Option<SharedView>,
+        session: Option<Bookmark>,
+    ) -> Result<Self, Error> {
+        let startup = config.clone();
+        let adapter = config.adapter_choice();
+        let palette_path = config.palette;
+        let palette = match palette_path.as_deref() {
+            Some(path) => Palette::load(path)?,
+            None => Palette::default(),
+        };
+
         let window_attributes = Window::default_attributes()
             .with_title("Mandelbrot")
-            .with_resizable(false)
-            .with_inner_size(winit::dpi::LogicalSize::new(1024.0, 768.0));
+            .with_resizable(true)
+            .with_inner_size(winit::dpi::LogicalSize::new(config.width, config.height));
 
-        let window = Arc::new(event_loop.create_window(window_attributes).unwrap());
-        let gpu = pollster::block_on(Wgpu::new(Arc::clone(&window)));
+        let window = Arc::new(event_loop.create_window(window_attributes)?);
+        let mut gpu = match config.backend {
+            Backend::Gpu => match pollster::block_on(Wgpu::new(Arc::clone(&window), &adapter)) {
+                Ok(gpu) => Some(gpu),
+                Err(error) => {
+                    eprintln!(
+                        "No GPU available, falling back to rendering on the CPU: {}",
+                        error
+                    );
+                    None
+                }
+            },
+            Backend::Cpu => None,
+        };
+        if let Some(gpu) = gpu.as_mut() {
+            gpu.write_palette(&palette);
+        }
+        let bookmarks = match Bookmarks::path().and_then(|path| Bookmarks::load(&path)) {
+            Ok(bookmarks) => bookmarks,
+            Err(error) => {
+                eprintln!("Starting without bookmarks: {}", error);
+                Bookmarks::default()
+            }
+        };
+        let ui = gpu
+            .as_ref()
+            .map(|gpu| ui::Ui::new(&window, &gpu.device, gpu.config.format));
+        let cpu_surface = if gpu.is_none() {
+            let context = softbuffer::Context::new(Arc::clone(&window))?;
+            Some(softbuffer::Surface::new(&context, Arc::clone(&window))?)
+        } else {
+            None
+        };
 
-        InnerApp {
+        let mut app = InnerApp {
             window,
+            backend: config.backend,
             gpu,
+            ui,
+            cpu_surface,
+            counts: cpu::EscapeCounts::default(),
+            count_stats: None,
+            tile_cache: cpu::TileCache::default(),
+            cpu_render: None,
+            frame: Vec::new(),
+            frame_bounds: (0, 0),
+            frame_style: None,
+            rendered_view: None,
+            stride: 1,
+            paused: false,
             focused: true,
             in_window: false,
             left_mouse: ElementState::Released,
-            center_point: (-0.5, 0.0),
-            zoom: 1.0,
+            modifiers: ModifiersState::empty(),
+            cursor_position: None,
+            selection_start: None,
+            drag_remembered: false,
+            drag_speed: (0.0, 0.0),
+            drag_step: Instant::now(),
+            glide: None,
+            glide_step: Instant::now(),
+            click_start: None,
+            center_point: config.center_point,
+            zoom: config.zoom,
+            target_camera: None,
+            animation_step: Instant::now(),
+            valid_camera: export::Camera {
+                center: mandelbrot::DEFAULT_CENTER_POINT,
+                zoom: mandelbrot::DEFAULT_ZOOM,
+            },
+            fractal_kind: FractalKind::default(),
+            escape_norm: EscapeNorm::default(),
+            initial_z: InitialZ::default(),
+            max_iterations: config.max_iterations,
+            adaptive_iterations: false,
+            bailout_squared: DEFAULT_BAILOUT_SQUARED,
+            julia_c: None,
+            power: DEFAULT_POWER,
+            orbit_trap: None,
+            trap_center: (0.0, 0.0),
+            trap_radius: DEFAULT_TRAP_RADIUS,
+            palette,
+            palette_path,
+            equalize: false,
+            fill_interior: false,
+            interior_periods: false,
+            periodicity_checking: config.periodicity_checking,
+            color_cycling: false,
+            palette_phase: 0.0,
+            color_cycle_step: Instant::now(),
+            gamma: config.gamma,
+            color_mapping: ColorMapping::default(),
+            dither: config.dither,
+            y_axis: config.y_axis,
+            supersampling: 1,
+            hud: false,
+            show_minimap: false,
+            minimap: minimap::Minimap::default(),
+            show_grid: false,
+            show_help: false,
+            measuring: false,
+            measurement: measure::Measurement::default(),
+            two_up: false,
+            cpu_half: None,
+            frame_times: hud::FrameTimes::default(),
+            title_updated: Instant::now(),
+            // No cap at 0, the interval being infinite
+            frame_interval: Duration::try_from_secs_f64(1.0 / config.max_fps).ok(),
+            frame_start: Instant::now(),
+            live_title: config.live_title,
+            verbose: config.verbose,
+            threads: cpu::thread_count(config.threads),
+            zoom_per_line: config.zoom_per_line,
+            pixels_per_line: config.pixels_per_line,
+            zoom_limits: (config.min_zoom, config.max_zoom),
+            startup,
+            kind_settings: BTreeMap::new(),
+            bookmarks,
+            history: History::default(),
+            typed_coordinates: None,
+        };
+        if config.kinds.contains_key(&app.fractal_kind) {
+            let settings = app.configured_kind_settings();
+            app.apply_kind_settings(settings);
+        }
+        if let Some(session) = session {
+            app.apply_bookmark(Bookmark {
+                center_point: config.center_point,
+                zoom: config.zoom,
+                max_iterations: config.max_iterations,
+                ..session
+            });
+        }
+        if let Some(view) = view {
+            app.apply_shared_view(view);
+        }
+        Ok(app)
+    }
+
+    /// What renders the frames, the CPU when the GPU was asked for but isn't available,
+    /// unlike `backend`.
+    pub fn active_backend(&self) -> Backend {
+        if self.gpu.is_some() {
+            Backend::Gpu
+        } else {
+            Backend::Cpu
+        }
+    }
+
+    pub fn redraw(&mut self) -> Result<(), Error> {
+        let start = Instant::now();
+        self.frame_start = start;
+        self.receive_coordinates();
+        if !self.paused {
+            self.step_animation(start);
+            self.step_glide(start);
+            self.step_color_cycle(start);
+        }
+        self.validate_camera();
+        let result = if self.active_backend() == Backend::Gpu {
+            self.redraw_gpu()
+        } else {
+            self.redraw_cpu()
+        };
+        let frame_time = start.elapsed();
+        self.frame_times.push(frame_time);
+        if self.verbose {
+            println!(
+                "{:?} frame time: {:.2?}",
+                self.window.inner_size(),
+                frame_time
+            );
+        }
+        if self.live_title && self.title_updated.elapsed() >= TITLE_UPDATE_INTERVAL {
+            self.title_updated = Instant::now();
+            let renderer = self.active_backend().name();
+            self.window
+                .set_title(&hud::title(renderer, self.zoom, &self.frame_times));
+        }
+        result
+    }
+
+    /// The status text shown while the HUD is on, followed by the measured distance,
+    /// which is shown even while it's off.
+    pub fn hud_text(&self) -> Option<String> {
+        let status = self.hud.then(|| {
+            hud::text(
+                self.center_point,
+                self.zoom,
+                self.iteration_limit(),
+                &self.frame_times,
+                self.cursor_point(),
+                // The counts are only those of the view shown while the CPU renders it
+                self.count_stats
+                    .as_ref()
+                    .filter(|_| self.active_backend() == Backend::Cpu),
+            )
+        });
+        let measured = self.measurement.text(
+            self.center_point,
+            self.zoom,
+            self.window.inner_size(),
+            self.y_axis,
+        );
+        match (status, measured) {
+            (Some(status), Some(measured)) => Some(format!("{}\n{}", status, measured)),
+            (status, measured) => status.or(measured),
+        }
+    }
+
+    /// The point of the complex plane under the cursor, None while it's outside of the
+    /// window.
+    pub fn cursor_point(&self) -> Option<(f64, f64)> {
+        let cursor = self.cursor_position.filter(|_| self.in_window)?;
+        Some(pixel_to_point(
+            self.center_point,
+            self.zoom,
+            self.window.inner_size(),
+            self.y_axis,
+            cursor,
+        ))
+    }
+
+    /// Where the endpoints of the measurement are in the window, in physical pixels.
+    pub fn measurement_pixels(&self) -> Vec<PhysicalPosition<f64>> {
+        self.measurement.pixels(
+            self.center_point,
+            self.zoom,
+            self.window.inner_size(),
+            self.y_axis,
+        )
+    }
+
+    /// Point the imaginary axis the other way, flipping the view upside down.
+    pub fn toggle_y_axis(&mut self) {
+        self.y_axis = self.y_axis.flipped();
+        // The counts rendered so far are the other way up
+        self.rendered_view = None;
+        println!("Imaginary axis points {:?}", self.y_axis);
+        self.window.request_redraw();
+    }
+
+    /// Start or stop placing the endpoints of a measurement with clicks, forgetting the
+    /// last one when stopping.
+    pub fn toggle_measuring(&mut self) {
+        self.measuring = !self.measuring;
+        if !self.measuring {
+            self.measurement.clear();
+        }
+        println!("Measuring: {}", self.measuring);
+        self.window.request_redraw();
+    }
+
+    /// The lines of the grid while it's on.
+    pub fn grid(&self) -> Option<grid::Grid> {
+        self.show_grid.then(|| {
+            grid::Grid::new(
+                self.center_point,
+                self.zoom,
+                self.window.inner_size(),
+                self.window.scale_factor(),
+                self.y_axis,
+            )
+        })
+    }
+
+    /// Where the minimap is drawn while it's on. The overview only shows the Mandelbrot
+    /// set, so it's left out for the other fractals.
+    pub fn minimap_layout(&self) -> Option<minimap::Layout> {
+        let mandelbrot = self.fractal_kind == FractalKind::Mandelbrot
+            && self.power == DEFAULT_POWER
+            && self.julia_c.is_none();
+        if !self.show_minimap || !mandelbrot {
+            return None;
+        }
+        minimap::Layout::new(
+            self.window.inner_size(),
+            self.window.scale_factor(),
+            self.center_point,
+            self.zoom,
+            self.y_axis,
+        )
+    }
+
+    /// Center the view on the point of the minimap that was clicked at `position`,
+    /// returning false if the minimap isn't there.
+    pub fn click_minimap(&mut self, position: PhysicalPosition<f64>) -> bool {
+        let Some(center) = self
+            .minimap_layout()
+            .and_then(|layout| layout.point_at(position))
+        else {
+            return false;
+        };
+        let zoom = self.target_camera().zoom;
+        self.animate_to(export::Camera { center, zoom });
+        true
+    }
+
+    /// Show the left half of the window rendered on the CPU next to the GPU's right
+    /// half, or go back to the GPU rendering all of it.
+    pub fn toggle_two_up(&mut self) {
+        if self.gpu.is_none() {
+            println!("The two-up view needs a GPU");
+            return;
+        }
+        self.two_up = !self.two_up;
+        if !self.two_up {
+            self.cpu_half = None;
+        }
+        println!("Two-up view: {}", self.two_up);
+        self.window.request_redraw();
+    }
+
+    /// Render the left half of the window on the CPU into `cpu_half`, unless it already
+    /// shows the current view.
+    fn render_cpu_half(&mut self) {
+        let window_resolution = self.window.inner_size();
+        let size = (
+            window_resolution.width as usize / 2,
+            window_resolution.height as usize,
+        );
+        if size.0 == 0 || size.1 == 0 {
+            self.cpu_half = None;
+            return;
+        }
+        // The half keeps the scale of the whole window, so its center is moved left by
+        // half of the width of the other half, leaving the top left corner in place
+        let (_, (view_width, _)) = center_to_start_conditions(
+            self.center_point,
+            self.zoom,
+            window_resolution,
+            self.y_axis,
+        );
+        let pixel_size = view_width / window_resolution.width as f64;
+        let shift = (window_resolution.width as usize - size.0) as f64 * pixel_size / 2.0;
+        let params = RenderParams {
+            camera: export::Camera {
+                center: (self.center_point.0 - shift, self.center_point.1),
+                zoom: self.zoom,
+            },
+            resolution: size,
+            iteration: self.iteration(),
+            style: self.style(),
+            threads: self.threads,
+        };
+        if self.cpu_half.as_ref().map(|(rendered, _)| rendered) != Some(&params) {
+            let pixels = mandelbrot::render(&params);
+            self.cpu_half = Some((params, pixels));
+        }
+    }
+
+    fn redraw_gpu(&mut self) -> Result<(), Error> {
+        let window_resolution = self.window.inner_size();
+        // The surface keeps its last size while the window is zero sized, the frame would
+        // be stretched into nothing anyway
+        if window_resolution.width == 0 || window_resolution.height == 0 {
+            return Ok(());
+        }
+        let selection = self.selection();
+        let hud = self.hud_text();
+        let help = self.show_help.then(keys::help);
+        let minimap_layout = self.minimap_layout();
+        let grid = self.grid();
+        let measurement = self.measurement_pixels();
+        // While paused, the CPU half rendered last is shown again
+        if self.two_up && !self.paused {
+            self.render_cpu_half();
+            // Rendering on the CPU takes a while, during which the window can be resized
+            // before the event saying so arrives. The half is left for the next frame to
+            // render again at the new size, rather than showing it at the old one.
+            if self.window.inner_size() != window_resolution {
+                self.window.request_redraw();
+                return Ok(());
+            }
+        }
+        let banner = precision_exhausted(self.center_point, self.zoom, window_resolution)
+            .then_some(PRECISION_WARNING);
+        let kind = self.fractal_kind;
+        // The panel is only there along with the GPU
+        let Some(panel) = self.ui.as_mut() else {
+            return Ok(());
+        };
+        // Run the panel first, so changes made with it show up in this frame already.
+        let ui_output = panel.run(
+            &self.window,
+            ui::Controls {
+                center_point: &mut self.center_point,
+                zoom: &mut self.zoom,
+                fractal_kind: &mut self.fractal_kind,
+                escape_norm: &mut self.escape_norm,
+                initial_z: &mut self.initial_z,
+                max_iterations: &mut self.max_iterations,
+                adaptive_iterations: &mut self.adaptive_iterations,
+                bailout_squared: &mut self.bailout_squared,
+                julia_c: &mut self.julia_c,
+                power: &mut self.power,
+                supersampling: &mut self.supersampling,
+                color_mapping: &mut self.color_mapping,
+                gamma: &mut self.gamma,
+                dither: &mut self.dither,
+                orbit_trap: &mut self.orbit_trap,
+                trap_center: &mut self.trap_center,
+                trap_radius: &mut self.trap_radius,
+            },
+            ui::Overlay {
+                banner,
+                selection,
+                hud: hud.as_deref(),
+                help: help.as_deref(),
+                grid: grid.as_ref(),
+                measurement: &measurement,
+                minimap: minimap_layout.map(|layout| (self.minimap.pixels(&layout), layout)),
+                cpu_half: self
+                    .cpu_half
+                    .as_ref()
+                    .map(|(params, pixels)| (pixels.as_slice(), params.resolution)),
+            },
+        );
+        if self.fractal_kind != kind {
+            self.switch_kind_settings(kind);
+        }
+
+        // While paused, the fractal of the last frame is shown again
+        let scene = (!self.paused).then(|| Scene {
+            camera: self.camera(),
+            iteration: self.iteration(),
+            supersampling: self.supersampling,
+            y_axis: self.y_axis,
+            phase: self.palette_phase,
+            gamma: self.gamma,
+            dither: self.dither,
+            mapping: self.color_mapping,
+        });
+
+        let (Some(gpu), Some(panel)) = (self.gpu.as_mut(), self.ui.as_mut()) else {
+            return Ok(());
+        };
+        // The surface is only resized by the event, and stretches a frame of another size
+        // to the window until then
+        if (gpu.config.width, gpu.config.height)
+            != (window_resolution.width, window_resolution.height)
+        {
+            gpu.resize(window_resolution);
+        }
+        let frame = gpu.surface.get_current_texture()?;
+
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("encoder"),
+            });
+        // Only timed while the frame times are printed, as reading the timestamps waits
+        // for the GPU to finish the frame
+        let timed = gpu.draw_fractal(&mut encoder, &frame.texture, scene, self.verbose);
+        panel.paint(
+            &self.window,
+            &gpu.device,
+            &gpu.queue,
+            &mut encoder,
+            &view,
+            ui_output,
+        );
+        gpu.queue.submit(Some(encoder.finish()));
+        if let Some(timestamps) = gpu.timestamps.as_ref().filter(|_| timed) {
+            println!(
+                "GPU render pass time: {:.2?}",
+                timestamps.read(&gpu.device)?
+            );
+        }
+
+        frame.present();
+
+        // Queue a RedrawRequested event.
+        //
+        // You only need to call this if you've determined that you need to redraw in
+        // applications which do not always need to. Applications that redraw continuously
+        // can render here instead.
+        // self.window.as_ref().unwrap().request_redraw();
+        Ok(())
+    }
+
+    /// Render the frame on the CPU and copy it into the window, without the settings panel.
+    fn redraw_cpu(&mut self) -> Result<(), Error> {
+        let window_resolution = self.window.inner_size();
+        let (Some(width), Some(height)) = (
+            NonZeroU32::new(window_resolution.width),
+            NonZeroU32::new(window_resolution.height),
+        ) else {
+            return Ok(());
+        };
+        let iteration = self.iteration();
+        let style = self.style();
+        let selection = self.selection();
+        let hud = self.hud_text();
+        let minimap_layout = self.minimap_layout();
+        let grid = self.grid();
+        let measurement = self.measurement_pixels();
+        let Some(surface) = self.cpu_surface.as_mut() else {
+            return Ok(());
+        };
+        surface.resize(width, height)?;
+
+        let bounds = (width.get() as usize, height.get() as usize);
+        // While paused, the last frame is shown again, unless the window was resized since
+        if !self.paused || self.frame_bounds != bounds {
+            self.render_cpu_frame(window_resolution, bounds, iteration, &style);
+            // The window can be resized while the frame renders, before the event saying
+            // so arrives. Rather than presenting a frame of the old size, a new one is
+            // rendered at the new size, which starts over with a coarse pass.
+            if self.window.inner_size() != window_resolution {
+                self.window.request_redraw();
+                return Ok(());
+            }
+        }
+        let Some(surface) = self.cpu_surface.as_mut() else {
+            return Ok(());
+        };
+        let mut buffer = surface.buffer_mut()?;
+        // softbuffer expects 0RGB pixels
+        mandelbrot::pack_0rgb(&self.frame, &mut buffer);
+        if let Some(grid) = grid {
+            grid::draw(&mut buffer, bounds, &grid, self.window.scale_factor());
+        }
+        if let Some(layout) = minimap_layout {
+            self.minimap.draw(&mut buffer, bounds, &layout);
         }
+        if let Some(corners) = selection {
+            draw_box(&mut buffer, bounds, corners);
+        }
+        measure::draw(
+            &mut buffer,
+            bounds,
+            &measurement,
+            self.window.scale_factor(),
+        );
+        if let Some(text) = hud {
+            hud::draw(&mut buffer, bounds, &text, self.window.scale_factor());
+        }
+        if self.cpu_render.is_some() {
+            hud::draw_busy(&mut buffer, bounds, self.window.scale_factor());
+        }
+        if self.show_help {
+            hud::dim(&mut buffer);
+            hud::draw(
+                &mut buffer,
+                bounds,
+                &keys::help(),
+                self.window.scale_factor(),
+            );
+        }
+        buffer.present()?;
+        Ok(())
+    }
+
+    /// Render the next pass of the frame of the current view into `frame`, or only color
+    /// it again once it's complete.
+    ///
+    /// A new view starts with a coarse pass that's quick to render, and every redraw after
+    /// it refines the frame until it's complete, unless the view changes again in between,
+    /// which starts over from the coarse pass. Once complete, only the colors change until
+    /// the view does.
+    ///
+    /// The last pass renders on a thread of its own, the previous one staying in `frame`
+    /// until it's done.
+    fn render_cpu_frame(
+        &mut self,
+        window_resolution: PhysicalSize<u32>,
+        bounds: (usize, usize),
+        iteration: cpu::Iteration,
+        style: &export::Style,
+    ) {
+        let view = (self.camera(), bounds, iteration, self.supersampling);
+        if self.rendered_view != Some(view) {
+            self.abort_cpu_render();
+        }
+        let complete = self.rendered_view == Some(view) && self.stride == 1;
+        let stride = if self.rendered_view == Some(view) {
+            (self.stride / 2).max(1)
+        } else {
+            COARSE_STRIDE
+        };
+        self.rendered_view = Some(view);
+
+        // The tiles are laid out with the imaginary axis pointing up, so the frame is
+        // rendered that way and flipped afterwards if it points down
+        let (top_left, (view_width, _)) =
+            center_to_start_conditions(self.center_point, self.zoom, window_resolution, YAxis::Up);
+        let upper_left = Complex::new(top_left.0, top_left.1);
+        let factor = self.supersampling as usize;
+        if !complete {
+            if stride > 1 {
+                // Every pixel of a coarse pass stands for a stride × stride block, which
+                // isn't worth caching the tiles of
+                let coarse = (bounds.0.div_ceil(stride), bounds.1.div_ceil(stride));
+                let pixel_size = stride as f64 * view_width / bounds.0 as f64;
+                self.counts.resize(coarse);
+                cpu::render_parallel(
+                    &mut self.counts,
+                    upper_left,
+                    upper_left
+                        + Complex::new(
+                            coarse.0 as f64 * pixel_size,
+                            -(coarse.1 as f64) * pixel_size,
+                        ),
+                    iteration,
+                    cpu::Parallelism::default(),
+                    self.threads,
+                );
+            } else {
+                // The samples are rendered as the pixels of a larger image, averaged at the end
+                let samples = (bounds.0 * factor, bounds.1 * factor);
+                let pixel_size = view_width / samples.0 as f64;
+                if !self.render_full_pass(samples, upper_left, pixel_size, iteration) {
+                    // The last pass stays in view until the full one is done, which
+                    // redraws the window
+                    return;
+                }
+            }
+            if self.y_axis == YAxis::Down {
+                self.counts.flip_rows();
+            }
+            self.count_stats = Some(self.counts.stats(iteration.limit));
+        }
+        if complete && self.frame_style.as_ref() == Some(style) {
+            return;
+        }
+        self.stride = stride;
+        self.frame_style = (stride == 1).then(|| style.clone());
+        let samples = self.counts.bounds();
+        let mut pixels = vec![0; 3 * samples.0 * samples.1];
+        self.counts.colorize(&mut pixels, iteration.limit, style);
+        self.frame = if self.stride > 1 {
+            // Refine the frame once the input that arrived in the meantime is handled
+            self.window.request_redraw();
+            cpu::upscale(&pixels, samples, self.stride, bounds)
+        } else {
+            cpu::downsample(pixels, bounds, factor)
+        };
+        self.frame_bounds = bounds;
+    }
+
+    /// Render the full resolution pass of `samples` on a thread of its own, or pick it up
+    /// once it's done. Returns whether `counts` holds it.
+    fn render_full_pass(
+        &mut self,
+        samples: (usize, usize),
+        upper_left: Complex<f64>,
+        pixel_size: f64,
+        iteration: cpu::Iteration,
+    ) -> bool {
+        let render = self.cpu_render.get_or_insert_with(|| {
+            let abort = Arc::new(AtomicBool::new(false));
+            let (sender, receiver) = mpsc::channel();
+            let mut tile_cache = std::mem::take(&mut self.tile_cache);
+            let mut counts = std::mem::take(&mut self.counts);
+            counts.resize(samples);
+            let (window, threads, aborted) =
+                (Arc::clone(&self.window), self.threads, Arc::clone(&abort));
+            std::thread::spawn(move || {
+                let done = tile_cache.render_abortable(
+                    &mut counts,
+                    upper_left,
+                    pixel_size,
+                    iteration,
+                    threads,
+                    &aborted,
+                );
+                if sender.send((tile_cache, counts, done)).is_ok() {
+                    // The pass is picked up by the next redraw
+                    window.request_redraw();
+                }
+            });
+            self.window.set_cursor(CursorIcon::Progress);
+            CpuRender { abort, receiver }
+        });
+        match render.receiver.try_recv() {
+            Ok((tile_cache, counts, done)) => {
+                self.cpu_render = None;
+                self.window.set_cursor(CursorIcon::Default);
+                self.tile_cache = tile_cache;
+                self.counts = counts;
+                done
+            }
+            Err(mpsc::TryRecvError::Empty) => false,
+            // The thread panicked, taking the counts and the tile cache with it
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.cpu_render = None;
+                self.window.set_cursor(CursorIcon::Default);
+                false
+            }
+        }
+    }
+
+    /// Stop the full resolution pass in progress, keeping the tiles it finished.
+    fn abort_cpu_render(&mut self) {
+        let Some(render) = self.cpu_render.take() else {
+            return;
+        };
+        render.abort.store(true, Ordering::Relaxed);
+        self.window.set_cursor(CursorIcon::Default);
+        // The thread stops after the tiles it's rendering, which doesn't take long
+        if let Ok((tile_cache, counts, _)) = render.receiver.recv() {
+            self.tile_cache = tile_cache;
+            self.counts = counts;
+        }
+    }
+
+    /// Match the surfaces to the new physical `size` of the window, after it's resized
+    /// or moved to a monitor with a different scale factor.
+    ///
+    /// A minimized window can be zero sized, which none of the surfaces can be resized
+    /// to. Nothing is drawn then, until a resize to a proper size redraws the window.
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        let (Some(width), Some(height)) =
+            (NonZeroU32::new(size.width), NonZeroU32::new(size.height))
+        else {
+            return;
+        };
+        if let Some(gpu) = self.gpu.as_mut() {
+            gpu.resize(size);
+        }
+        if let Some(surface) = self.cpu_surface.as_mut() {
+            if let Err(error) = surface.resize(width, height) {
+                eprintln!("Failed to resize the surface: {}", error);
+            }
+        }
+        self.window.request_redraw();
+    }
+
+    /// The camera framing the whole of the current fractal, or the framing configured for
+    /// it.
+    pub fn default_camera(&self) -> export::Camera {
+        let (mut center, mut zoom) =
+            default_camera_settings(self.fractal_kind, self.power, self.julia_c.is_some());
+        // The framing configured is of the fractal itself, not of its Julia sets or of
+        // higher powers
+        let configured = self.startup.kinds.get(&self.fractal_kind);
+        if let Some(configured) =
+            configured.filter(|_| self.julia_c.is_none() && self.power == DEFAULT_POWER)
+        {
+            center = configured.center_point.unwrap_or(center);
+            zoom = configured.zoom.unwrap_or(zoom);
+        }
+        export::Camera { center, zoom }
+    }
+
+    /// Frame the whole fractal after switching to another one, unless the view was moved
+    /// away from framing the whole of the `previous` one.
+    fn reframe(&mut self, previous: export::Camera) {
+        if self.target_camera() == previous {
+            self.reset_camera(true);
+        } else {
+            self.window.request_redraw();
+        }
+    }
+
+    /// True while the settings panel uses the mouse, so it shouldn't move the view.
+    pub fn ui_wants_pointer(&self) -> bool {
+        self.ui.as_ref().is_some_and(|ui| ui.wants_pointer())
+    }
+
+    /// Move the camera back to the default view of the current fractal, animated unless
+    /// `instant`.
+    pub fn reset_camera(&mut self, instant: bool) {
+        let camera = self.default_camera();
+        if instant {
+            self.remember_camera();
+            self.set_camera(camera);
+        } else {
+            self.animate_to(camera);
+        }
+    }
+
+    /// Zoom to `zoom`, keeping the point under the cursor in place, or the view center
+    /// if the cursor's position isn't known.
+    ///
+    /// The zoom is animated, a zoom during the animation of another continues from
+    /// where the previous one is heading.
+    pub fn zoom_towards_cursor(&mut self, zoom: f64) {
+        // Scrolling out far enough would flip the view upside down
+        if zoom <= self.zoom_limits.0 {
+            return;
+        }
+        let zoom = zoom.min(self.zoom_limits.1);
+        let target = self.target_camera();
+        let mut center = target.center;
+        if let Some(cursor) = self.cursor_position {
+            let window_resolution = self.window.inner_size();
+            let before = pixel_to_point(
+                target.center,
+                target.zoom,
+                window_resolution,
+                self.y_axis,
+                cursor,
+            );
+            let after = pixel_to_point(target.center, zoom, window_resolution, self.y_axis, cursor);
+            center = (
+                center.0 + (before.0 - after.0),
+                center.1 + (before.1 - after.1),
+            );
+        }
+        self.animate_to(export::Camera { center, zoom });
+    }
+
+    /// The camera the view ends up with, once the animation in progress is done.
+    pub fn target_camera(&self) -> export::Camera {
+        self.target_camera.unwrap_or_else(|| self.camera())
+    }
+
+    /// Start animating the camera towards `camera`.
+    pub fn animate_to(&mut self, camera: export::Camera) {
+        self.remember_camera();
+        self.glide = None;
+        if self.target_camera.is_none() {
+            self.animation_step = Instant::now();
+        }
+        self.target_camera = Some(camera);
+        self.window.request_redraw();
+    }
+
+    /// Move the camera towards its target by the time passed since the last step.
+    ///
+    /// The camera covers the same share of the remaining distance in the same time,
+    /// so the movement eases out independently of the frame rate.
+    pub fn step_animation(&mut self, now: Instant) {
+        let Some(target) = self.target_camera else {
+            return;
+        };
+        let elapsed = now.duration_since(self.animation_step).as_secs_f64();
+        self.animation_step = now;
+        let camera = export::interpolate_camera(
+            self.camera(),
+            target,
+            1.0 - (-elapsed / CAMERA_SMOOTHING).exp(),
+        );
+        // Done once the rest of the way is well below a pixel
+        let window_resolution = self.window.inner_size();
+        let (_, (width, _)) =
+            center_to_start_conditions(camera.center, camera.zoom, window_resolution, YAxis::Up);
+        let pixel = width / window_resolution.width.max(1) as f64;
+        let close = (camera.zoom / target.zoom).ln().abs() < 1.0e-3
+            && (camera.center.0 - target.center.0).abs() < 0.1 * pixel
+            && (camera.center.1 - target.center.1).abs() < 0.1 * pixel;
+        if close {
+            self.target_camera = None;
+            (self.center_point, self.zoom) = (target.center, target.zoom);
+        } else {
+            (self.center_point, self.zoom) = (camera.center, camera.zoom);
+        }
+    }
+
+    /// True while the camera is on its way to a target, the view glides on after a flick,
+    /// or the colors are cycling.
+    pub fn animating(&self) -> bool {
+        (self.target_camera.is_some() || self.glide.is_some() || self.color_cycling) && !self.paused
+    }
+
+    /// Start or stop cycling the colors, which stay where they are when stopped.
+    pub fn toggle_color_cycling(&mut self) {
+        self.color_cycling = !self.color_cycling;
+        self.color_cycle_step = Instant::now();
+        println!("Color cycling: {}", self.color_cycling);
+        self.window.request_redraw();
+    }
+
+    /// Cycle the colors by the time passed since the last step.
+    pub fn step_color_cycle(&mut self, now: Instant) {
+        if !self.color_cycling {
+            return;
+        }
+        let elapsed = now.duration_since(self.color_cycle_step).as_secs_f32();
+        self.color_cycle_step = now;
+        self.palette_phase = (self.palette_phase + elapsed * COLOR_CYCLE_SPEED).fract();
+    }
+
+    /// Freeze the current frame, or go back to rendering the view as it changes.
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        println!("Paused: {}", self.paused);
+        // Continue an animation from where it was paused instead of jumping ahead
+        self.animation_step = Instant::now();
+        self.color_cycle_step = Instant::now();
+        self.window.request_redraw();
+    }
+
+    /// Move the camera with a mouse drag of `delta`.
+    ///
+    /// Only the camera before the first movement of a drag is remembered, so undoing
+    /// goes back to where the whole drag started.
+    pub fn drag(&mut self, delta: (f64, f64)) {
+        self.target_camera = None;
+        self.glide = None;
+        if !self.drag_remembered {
+            self.remember_camera();
+            self.drag_remembered = true;
+            self.drag_speed = (0.0, 0.0);
+            self.drag_step = Instant::now();
+        }
+        // Average the speed over the last moments, the movements coming too quickly and
+        // unevenly to take the speed of any single one of them
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.drag_step).as_secs_f64().max(1.0e-3);
+        self.drag_step = now;
+        let weight = 1.0 - (-elapsed / DRAG_SPEED_SMOOTHING).exp();
+        self.drag_speed = (
+            self.drag_speed.0 + (delta.0 / elapsed - self.drag_speed.0) * weight,
+            self.drag_speed.1 + (delta.1 / elapsed - self.drag_speed.1) * weight,
+        );
+        self.pan(delta);
+    }
+
+    /// Move the view along with `delta` pixels of mouse movement, without remembering
+    /// the camera before.
+    fn pan(&mut self, delta: (f64, f64)) {
+        // Move the view by a pixel's worth of the plane for every pixel the mouse moves,
+        // in the opposite direction, so the image follows the cursor as if grabbed
+        let window_resolution = self.window.inner_size();
+        let (_, (width, height)) = center_to_start_conditions(
+            self.center_point,
+            self.zoom,
+            window_resolution,
+            self.y_axis,
+        );
+        self.center_point = (
+            self.center_point.0 - delta.0 * width / window_resolution.width.max(1) as f64,
+            // The height is negative while the imaginary axis points down, like the
+            // window's y axis does, and positive while it points up against it
+            self.center_point.1 + delta.1 * height / window_resolution.height.max(1) as f64,
+        );
+        self.window.request_redraw();
+    }
+
+    /// Keep the view gliding on when a drag is released while the mouse is still moving
+    /// fast, rather than after it came to rest or as the end of a click.
+    pub fn start_glide(&mut self) {
+        let flicked = self.drag_remembered
+            && self.click_start.is_none()
+            && self.drag_step.elapsed() < FLICK_TIMEOUT
+            && self.drag_speed.0.hypot(self.drag_speed.1) >= MIN_FLICK_SPEED;
+        if flicked {
+            self.glide = Some(self.drag_speed);
+            self.glide_step = Instant::now();
+            self.window.request_redraw();
+        }
+    }
+
+    /// Move the gliding view by the time passed since the last step, slowing it down
+    /// until it comes to rest.
+    pub fn step_glide(&mut self, now: Instant) {
+        let Some(speed) = self.glide else {
+            return;
+        };
+        let elapsed = now.duration_since(self.glide_step).as_secs_f64();
+        self.glide_step = now;
+        self.pan((speed.0 * elapsed, speed.1 * elapsed));
+        let decay = (-elapsed / GLIDE_DECAY).exp();
+        let speed = (speed.0 * decay, speed.1 * decay);
+        self.glide = (speed.0.hypot(speed.1) >= MIN_GLIDE_SPEED).then_some(speed);
+    }
+
+    /// Start a click at the cursor when the left button is pressed without modifiers,
+    /// which are taken by the box zoom and the Julia constant scrubbing.
+    pub fn start_click(&mut self) {
+        let plain = !self.modifiers.control_key() && !self.modifiers.shift_key();
+        self.click_start = self
+            .cursor_position
+            .filter(|_| plain && self.focused && self.in_window && !self.ui_wants_pointer());
+    }
+
+    /// Forget the click in progress once the cursor moves too far for one.
+    pub fn follow_click(&mut self, position: PhysicalPosition<f64>) {
+        if let Some(start) = self.click_start {
+            if (position.x - start.x).hypot(position.y - start.y) > CLICK_DISTANCE {
+                self.click_start = None;
+            }
+        }
+    }
+
+    /// Center the view on the point under the cursor if the left button is released
+    /// without having been dragged, or place an endpoint there while measuring.
+    ///
+    /// The view goes back to where it was before the few pixels it was dragged by since
+    /// the press, so the click adds a single camera to the history.
+    pub fn end_click(&mut self) {
+        let (Some(_), Some(cursor)) = (self.click_start.take(), self.cursor_position) else {
+            return;
+        };
+        let window_resolution = self.window.inner_size();
+        let center = pixel_to_point(
+            self.center_point,
+            self.zoom,
+            window_resolution,
+            self.y_axis,
+            cursor,
+        );
+        let zoom = self.target_camera().zoom;
+        if self.drag_remembered {
+            self.undo_camera();
+        }
+        if self.measuring {
+            self.measurement.click(center);
+            self.window.request_redraw();
+        } else {
+            self.animate_to(export::Camera { center, zoom });
+        }
+    }
+
+    /// Put the current camera into the history, before navigating away from it.
+    pub fn remember_camera(&mut self) {
+        self.history.push(self.camera());
+    }
+
+    /// Go back to the camera before the last navigation.
+    pub fn undo_camera(&mut self) {
+        match self.history.undo(self.camera()) {
+            Some(camera) => self.set_camera(camera),
+            None => println!("Nothing to undo"),
+        }
+    }
+
+    /// Go forward to the camera the last undo went back from.
+    pub fn redo_camera(&mut self) {
+        match self.history.redo(self.camera()) {
+            Some(camera) => self.set_camera(camera),
+            None => println!("Nothing to redo"),
+        }
+    }
+
+    /// Go back to the last valid camera when the current one has a center or zoom that
+    /// isn't finite, or a view too small for f64, which would render as garbage or black,
+    /// warning about it.
+    fn validate_camera(&mut self) {
+        let window_resolution = self.window.inner_size();
+        if window_resolution.width == 0 || window_resolution.height == 0 {
+            return;
+        }
+        if view_is_valid(self.center_point, self.zoom, window_resolution) {
+            self.valid_camera = self.camera();
+            return;
+        }
+        eprintln!(
+            "The view at center {:?} and zoom {:?} can't be rendered, going back to center \
+             {:?} and zoom {:?}",
+            self.center_point, self.zoom, self.valid_camera.center, self.valid_camera.zoom
+        );
+        self.set_camera(self.valid_camera);
+    }
+
+    fn set_camera(&mut self, camera: export::Camera) {
+        self.target_camera = None;
+        self.glide = None;
+        self.center_point = camera.center;
+        self.zoom = camera.zoom;
+        self.window.request_redraw();
+    }
+
+    /// The opposite corners of the box zoom selection in progress, the cursor being the end.
+    pub fn selection(&self) -> Option<(PhysicalPosition<f64>, PhysicalPosition<f64>)> {
+        Some((self.selection_start?, self.cursor_position?))
+    }
+
+    /// Start the box zoom selection at the cursor when the left button is pressed with
+    /// Ctrl held, and zoom to the box when it's released.
+    pub fn select_box(&mut self, state: ElementState) {
+        match state {
+            ElementState::Pressed => {
+                if self.modifiers.control_key()
+                    && self.focused
+                    && self.in_window
+                    && !self.ui_wants_pointer()
+                {
+                    self.selection_start = self.cursor_position;
+                }
+            }
+            ElementState::Released => {
+                if let Some((start, end)) = self.selection() {
+                    self.zoom_to_box(start, end);
+                }
+                self.selection_start = None;
+                self.window.request_redraw();
+            }
+        }
+    }
+
+    /// Frame the box between the window positions `start` and `end`, keeping the aspect
+    /// ratio of the window, so the view fits the whole box.
+    ///
+    /// Boxes that aren't dragged down and to the right, or that are barely dragged at all,
+    /// are ignored.
+    pub fn zoom_to_box(&mut self, start: PhysicalPosition<f64>, end: PhysicalPosition<f64>) {
+        let (width, height) = (end.x - start.x, end.y - start.y);
+        if width < MIN_SELECTION || height < MIN_SELECTION {
+            return;
+        }
+        self.remember_camera();
+        self.target_camera = None;
+        self.glide = None;
+        let window_resolution = self.window.inner_size();
+        let middle = PhysicalPosition::new((start.x + end.x) / 2.0, (start.y + end.y) / 2.0);
+        self.center_point = pixel_to_point(
+            self.center_point,
+            self.zoom,
+            window_resolution,
+            self.y_axis,
+            middle,
+        );
+        self.zoom *=
+            (window_resolution.width as f64 / width).min(window_resolution.height as f64 / height);
+        self.zoom = self.zoom.min(self.zoom_limits.1);
+        println!("Zoomed to {:?} at {}", self.center_point, self.zoom);
+    }
+
+    /// Switch to the next fractal kind, framing the whole of it unless the view was moved.
+    pub fn next_fractal_kind(&mut self) {
+        let previous = self.default_camera();
+        let previous_kind = self.fractal_kind;
+        self.fractal_kind = self.fractal_kind.next();
+        println!("Fractal: {:?}", self.fractal_kind);
+        self.switch_kind_settings(previous_kind);
+        self.reframe(previous);
+    }
+
+    /// Keep the settings of the fractal kind `previous` for switching back to it, and take
+    /// the ones last used with the current kind, or its configured ones the first time.
+    fn switch_kind_settings(&mut self, previous: FractalKind) {
+        let kept = KindSettings {
+            max_iterations: self.max_iterations,
+            color_mapping: self.color_mapping,
+            palette: self.palette.clone(),
+            palette_path: self.palette_path.clone(),
+        };
+        self.kind_settings.insert(previous, kept);
+        let settings = match self.kind_settings.remove(&self.fractal_kind) {
+            Some(settings) => settings,
+            None => self.configured_kind_settings(),
+        };
+        self.apply_kind_settings(settings);
+    }
+
+    /// The settings of the current fractal kind in the config file, those left out of its
+    /// table being the ones of the whole file.
+    fn configured_kind_settings(&self) -> KindSettings {
+        let configured = self
+            .startup
+            .kinds
+            .get(&self.fractal_kind)
+            .cloned()
+            .unwrap_or_default();
+        let (palette, palette_path) = match configured.palette.or(self.startup.palette.clone()) {
+            Some(path) => match Palette::load(&path) {
+                Ok(palette) => (palette, Some(path)),
+                Err(error) => {
+                    eprintln!("Keeping the current palette: {}", error);
+                    (self.palette.clone(), self.palette_path.clone())
+                }
+            },
+            None => (Palette::default(), None),
+        };
+        KindSettings {
+            max_iterations: configured
+                .max_iterations
+                .unwrap_or(self.startup.max_iterations),
+            color_mapping: configured.color_mapping.unwrap_or_default(),
+            palette,
+            palette_path,
+        }
+    }
+
+    fn apply_kind_settings(&mut self, settings: KindSettings) {
+        self.max_iterations = settings.max_iterations;
+        self.color_mapping = settings.color_mapping;
+        if let Some(gpu) = self.gpu.as_mut() {
+            gpu.write_palette(&settings.palette);
+        }
+        self.palette = settings.palette;
+        self.palette_path = settings.palette_path;
+    }
+
+    /// Restore every runtime tweakable setting to the one the application started with,
+    /// and frame the whole fractal.
+    pub fn reset_all(&mut self) {
+        self.fractal_kind = FractalKind::default();
+        self.escape_norm = EscapeNorm::default();
+        self.initial_z = InitialZ::default();
+        self.max_iterations = self.startup.max_iterations;
+        self.adaptive_iterations = false;
+        self.bailout_squared = DEFAULT_BAILOUT_SQUARED;
+        self.julia_c = None;
+        self.power = DEFAULT_POWER;
+        self.orbit_trap = None;
+        self.trap_center = (0.0, 0.0);
+        self.trap_radius = DEFAULT_TRAP_RADIUS;
+        self.supersampling = 1;
+        self.color_cycling = false;
+        self.palette_phase = 0.0;
+        self.color_mapping = ColorMapping::default();
+        self.kind_settings.clear();
+        if self.startup.kinds.contains_key(&self.fractal_kind) {
+            let settings = self.configured_kind_settings();
+            self.apply_kind_settings(settings);
+        }
+        self.gamma = self.startup.gamma;
+        self.equalize = false;
+        self.dither = self.startup.dither;
+        self.fill_interior = false;
+        self.y_axis = self.startup.y_axis;
+        // The counts rendered so far may be the other way up
+        self.rendered_view = None;
+        self.interior_periods = false;
+        self.periodicity_checking = self.startup.periodicity_checking;
+        self.reset_camera(true);
+    }
+
+    /// Multiply the iteration limit by `factor`, keeping it within [`ITERATION_LIMITS`].
+    pub fn scale_max_iterations(&mut self, factor: f32) {
+        let scaled = (self.max_iterations as f32 * factor).round() as u32;
+        self.max_iterations = scaled.clamp(*ITERATION_LIMITS.start(), *ITERATION_LIMITS.end());
+        println!("Iteration limit: {}", self.max_iterations);
+        self.window.request_redraw();
+    }
+
+    /// Load the palette file given on the command line again, to see the changes made to it.
+    pub fn reload_palette(&mut self) {
+        let Some(path) = self.palette_path.as_deref() else {
+            println!("No palette file to reload, pass one with --palette <path>");
+            return;
+        };
+        match Palette::load(path) {
+            Ok(palette) => {
+                if let Some(gpu) = self.gpu.as_mut() {
+                    gpu.write_palette(&palette);
+                }
+                self.palette = palette;
+                println!("Reloaded the palette from {}", path.display());
+                self.window.request_redraw();
+            }
+            Err(error) => eprintln!("Keeping the current palette: {}", error),
+        }
+    }
+
+    /// Switch dithering the colors against banding on or off.
+    pub fn toggle_dither(&mut self) {
+        self.dither = !self.dither;
+        println!("Dithering: {}", self.dither);
+        self.window.request_redraw();
+    }
+
+    /// Switch the histogram equalization of the colors on or off.
+    ///
+    /// It needs the counts of the whole frame, so it only applies to what the CPU renders,
+    /// including the exports.
+    pub fn toggle_equalize(&mut self) {
+        self.equalize = !self.equalize;
+        println!("Histogram equalization: {}", self.equalize);
+        if self.gpu.is_some() {
+            println!("The GPU renders aren't equalized, only the exports");
+        }
+        self.window.request_redraw();
+    }
+
+    /// Switch filling the interior of the set without iterating it on the CPU on or off.
+    pub fn toggle_fill_interior(&mut self) {
+        self.fill_interior = !self.fill_interior;
+        println!("Fill the interior: {}", self.fill_interior);
+        if self.gpu.is_some() {
+            println!("The GPU iterates every pixel, only the exports fill the interior");
+        }
+        self.window.request_redraw();
+    }
+
+    /// Switch coloring the interior by the periods of its orbits on the CPU on or off.
+    pub fn toggle_interior_periods(&mut self) {
+        self.interior_periods = !self.interior_periods;
+        println!("Color the interior by period: {}", self.interior_periods);
+        if self.gpu.is_some() {
+            println!("The GPU leaves the interior black, only the exports show the periods");
+        }
+        self.window.request_redraw();
+    }
+
+    /// Step the gamma to the next one of [`GAMMAS`], or back to the first one from a
+    /// gamma set in the settings panel.
+    pub fn next_gamma(&mut self) {
+        self.gamma = match GAMMAS.iter().position(|gamma| *gamma == self.gamma) {
+            Some(index) => GAMMAS[(index + 1) % GAMMAS.len()],
+            None => GAMMAS[0],
+        };
+        println!("Gamma: {}", self.gamma);
+        self.window.request_redraw();
+    }
+
+    pub fn next_color_mapping(&mut self) {
+        self.color_mapping = self.color_mapping.next();
+        println!("Color mapping: {:?}", self.color_mapping);
+        self.window.request_redraw();
+    }
+
+    /// Step the supersampling to the next factor, from no supersampling up to
+    /// [`MAX_SUPERSAMPLING`] and around.
+    pub fn next_supersampling(&mut self) {
+        self.supersampling = self.supersampling % MAX_SUPERSAMPLING + 1;
+        println!(
+            "Supersampling: {0}x{0} samples per pixel",
+            self.supersampling
+        );
+        self.window.request_redraw();
+    }
+
+    /// Switch between the fixed and the zoom dependent iteration limit.
+    pub fn toggle_adaptive_iterations(&mut self) {
+        self.adaptive_iterations = !self.adaptive_iterations;
+        println!(
+            "Adaptive iterations: {}, limit: {}",
+            self.adaptive_iterations,
+            self.iteration_limit()
+        );
+        self.window.request_redraw();
+    }
+
+    /// The iteration limit used for rendering the current view.
+    pub fn iteration_limit(&self) -> u32 {
+        iteration_limit(self.max_iterations, self.adaptive_iterations, self.zoom)
+    }
+
+    /// Switch between the Mandelbrot set and the Julia set of the point at the view center.
+    pub fn toggle_julia(&mut self) {
+        let previous = self.default_camera();
+        self.julia_c = match self.julia_c {
+            Some(_) => None,
+            None => Some(Complex::new(
+                self.center_point.0 as f32,
+                self.center_point.1 as f32,
+            )),
+        };
+        println!("Julia constant: {:?}", self.julia_c);
+        self.reframe(previous);
+    }
+
+    /// Move the Julia constant along with a mouse movement of `delta`.
+    pub fn scrub_julia(&mut self, delta: (f64, f64)) {
+        if let Some(julia_c) = self.julia_c.as_mut() {
+            julia_c.re += delta.0 as f32 / 1000.0;
+            // The constant moves along with the mouse on the imaginary axis of the view
+            match self.y_axis {
+                YAxis::Up => julia_c.im -= delta.1 as f32 / 1000.0,
+                YAxis::Down => julia_c.im += delta.1 as f32 / 1000.0,
+            }
+            self.window.request_redraw();
+        }
+    }
+
+    /// The iteration settings for rendering the current view on the CPU.
+    pub fn iteration(&self) -> cpu::Iteration {
+        cpu::Iteration {
+            kind: self.fractal_kind,
+            limit: self.iteration_limit(),
+            norm: self.escape_norm,
+            initial_z: self.initial_z,
+            bailout_squared: self.bailout_squared,
+            julia_c: self
+                .julia_c
+                .map(|julia_c| Complex::new(julia_c.re as f64, julia_c.im as f64)),
+            power: self.power,
+            trap: self.orbit_trap.map(|shape| cpu::OrbitTrap {
+                shape,
+                center: Complex::new(self.trap_center.0, self.trap_center.1),
+                radius: self.trap_radius,
+            }),
+            fill_interior: self.fill_interior,
+            interior_periods: self.interior_periods,
+            periodicity_checking: self.periodicity_checking,
+        }
+    }
+
+    /// Change the power z is raised to by `change`, keeping it within [`POWER_LIMITS`],
+    /// and frame the whole of the resulting fractal unless the view was moved.
+    pub fn change_power(&mut self, change: i32) {
+        let previous = self.default_camera();
+        let power = self.power.saturating_add_signed(change);
+        self.power = power.clamp(*POWER_LIMITS.start(), *POWER_LIMITS.end());
+        println!("Power: {}", self.power);
+        self.reframe(previous);
+    }
+
+    /// Cycle the orbit trap through the shapes and back to coloring by escape counts.
+    pub fn next_orbit_trap(&mut self) {
+        self.orbit_trap = match self.orbit_trap {
+            None => Some(TrapShape::Point),
+            Some(TrapShape::Point) => Some(TrapShape::Line),
+            Some(TrapShape::Line) => Some(TrapShape::Circle),
+            Some(TrapShape::Circle) => None,
+        };
+        println!("Orbit trap: {:?}", self.orbit_trap);
+        self.window.request_redraw();
+    }
+
+    /// The current view and iteration settings, for saving them.
+    pub fn bookmark(&self) -> Bookmark {
+        Bookmark {
+            center_point: self.center_point,
+            zoom: self.zoom,
+            fractal_kind: self.fractal_kind,
+            escape_norm: self.escape_norm,
+            initial_z: self.initial_z,
+            max_iterations: self.max_iterations,
+            adaptive_iterations: self.adaptive_iterations,
+            bailout_squared: self.bailout_squared,
+            julia_c: self.julia_c.map(|julia_c| (julia_c.re, julia_c.im)),
+            power: self.power,
+        }
+    }
+
+    /// Save the current view into the bookmark `slot`, and the bookmarks to disk.
+    pub fn save_bookmark(&mut self, slot: u8) {
+        self.bookmarks.set(slot, self.bookmark());
+        match Bookmarks::path().and_then(|path| self.bookmarks.save(&path)) {
+            Ok(()) => println!("Saved bookmark {}", slot),
+            Err(error) => eprintln!("Failed to save the bookmarks: {}", error),
+        }
+    }
+
+    /// Jump to the view saved in the bookmark `slot`.
+    pub fn jump_to_bookmark(&mut self, slot: u8) {
+        let Some(bookmark) = self.bookmarks.get(slot).cloned() else {
+            println!(
+                "No bookmark {} saved yet, save one with Ctrl+{}",
+                slot, slot
+            );
+            return;
+        };
+        self.apply_bookmark(bookmark);
+        println!("Jumped to bookmark {}", slot);
+    }
+
+    /// Switch to the view and the iteration settings of `bookmark`.
+    pub fn apply_bookmark(&mut self, bookmark: Bookmark) {
+        self.remember_camera();
+        self.target_camera = None;
+        self.center_point = bookmark.center_point;
+        self.zoom = bookmark.zoom;
+        self.fractal_kind = bookmark.fractal_kind;
+        self.escape_norm = bookmark.escape_norm;
+        self.initial_z = bookmark.initial_z;
+        self.max_iterations = bookmark.max_iterations;
+        self.adaptive_iterations = bookmark.adaptive_iterations;
+        self.bailout_squared = bookmark.bailout_squared;
+        self.julia_c = bookmark.julia_c.map(|(re, im)| Complex::new(re, im));
+        self.power = bookmark.power;
+        self.window.request_redraw();
+    }
+
+    /// Save the view and the settings to restore them on the next start.
+    pub fn save_session(&self) {
+        let session = Session {
+            backend: self.backend,
+            palette: self.palette_path.clone(),
+            view: self.bookmark(),
+        };
+        if let Err(error) = Session::path().and_then(|path| session.save(&path)) {
+            eprintln!("Failed to save the session: {}", error);
+        }
+    }
+
+    /// Switch to a view shared as a string, along with its palette.
+    pub fn apply_shared_view(&mut self, view: SharedView) {
+        self.apply_bookmark(view.bookmark);
+        if let Some(gpu) = self.gpu.as_mut() {
+            gpu.write_palette(&view.palette);
+        }
+        self.palette = view.palette;
+    }
+
+    /// Everything the current view is rendered with, at the window's resolution.
+    pub fn render_config(&self) -> RenderConfig {
+        let size = self.window.inner_size();
+        RenderConfig {
+            center_point: self.center_point,
+            zoom: self.zoom,
+            resolution: (size.width as usize, size.height as usize),
+            max_iterations: self.max_iterations,
+            adaptive_iterations: self.adaptive_iterations,
+            bailout_squared: self.bailout_squared,
+            fractal_kind: self.fractal_kind,
+            power: self.power,
+            escape_norm: self.escape_norm,
+            initial_z: self.initial_z,
+            julia_c: self
+                .julia_c
+                .map(|julia_c| (julia_c.re as f64, julia_c.im as f64)),
+            orbit_trap: self.orbit_trap,
+            trap_center: self.trap_center,
+            trap_radius: self.trap_radius,
+            fill_interior: self.fill_interior,
+            interior_periods: self.interior_periods,
+            periodicity_checking: self.periodicity_checking,
+            palette: self.palette.stops().to_vec(),
+            color_mapping: self.color_mapping,
+            equalize: self.equalize,
+            phase: self.palette_phase,
+            gamma: self.gamma,
+            supersampling: self.supersampling as usize,
+            y_axis: self.y_axis,
+            dither: self.dither,
+        }
+    }
+
+    /// Save the render configuration of the current view into a JSON file, to be
+    /// rendered again with `--config`.
+    pub fn save_render_config(&self) {
+        let render_config = self.render_config();
+        let (width, height) = render_config.resolution;
+        let filename = format!(
+            "mandelbrot_{}_{}_z{}_{}x{}.json",
+            self.center_point.0, self.center_point.1, self.zoom, width, height
+        );
+        match render_config.save(Path::new(&filename)) {
+            Ok(()) => println!("Saved the render configuration to {}", filename),
+            Err(error) => eprintln!("Failed to save the render configuration: {}", error),
+        }
+    }
+
+    /// Copy the current view as a string to the clipboard, to be passed to `--view`.
+    pub fn copy_view(&self) {
+        let view = SharedView {
+            bookmark: self.bookmark(),
+            palette: self.palette.clone(),
+        }
+        .to_string();
+        match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(&view)) {
+            Ok(()) => println!("Copied the view to the clipboard: {}", view),
+            Err(error) => eprintln!("{}, the view is: {}", Error::from(error), view),
+        }
+    }
+
+    /// Everything that decides what the window shows, one setting per line, for pasting
+    /// into bug reports.
+    ///
+    /// The floats are printed with the shortest decimals that parse back into the exact
+    /// same values, so the view can be reproduced from them.
+    pub fn state_dump(&self) -> String {
+        let size = self.window.inner_size();
+        let renderer = self.active_backend().name();
+        let lines = [
+            ("center_re", format!("{:?}", self.center_point.0)),
+            ("center_im", format!("{:?}", self.center_point.1)),
+            ("zoom", format!("{:?}", self.zoom)),
+            ("zoom_per_line", format!("{:?}", self.zoom_per_line)),
+            ("pixels_per_line", format!("{:?}", self.pixels_per_line)),
+            ("zoom_limits", format!("{:?}", self.zoom_limits)),
+            ("max_iterations", self.max_iterations.to_string()),
+            ("adaptive_iterations", self.adaptive_iterations.to_string()),
+            ("iteration_limit", self.iteration_limit().to_string()),
+            ("fractal_kind", format!("{:?}", self.fractal_kind)),
+            ("power", self.power.to_string()),
+            ("escape_norm", format!("{:?}", self.escape_norm)),
+            ("initial_z", format!("{:?}", self.initial_z)),
+            ("bailout_squared", format!("{:?}", self.bailout_squared)),
+            ("julia_c", format!("{:?}", self.julia_c)),
+            ("orbit_trap", format!("{:?}", self.orbit_trap)),
+            ("supersampling", self.supersampling.to_string()),
+            ("color_mapping", format!("{:?}", self.color_mapping)),
+            ("y_axis", format!("{:?}", self.y_axis)),
+            (
+                "backend",
+                format!("{:?}, rendering on the {}", self.backend, renderer),
+            ),
+            ("window", format!("{}x{}", size.width, size.height)),
+            ("scale_factor", format!("{:?}", self.window.scale_factor())),
+            ("threads", self.threads.to_string()),
+            (
+                "max_fps",
+                self.frame_interval
+                    .map_or("unlimited".to_string(), |interval| {
+                        format!("{:.1}", 1.0 / interval.as_secs_f64())
+                    }),
+            ),
+            ("paused", self.paused.to_string()),
+        ];
+        let mut dump = String::from("--- view state ---");
+        for (name, value) in lines {
+            dump.push_str(&format!("\n{:<20}{}", name, value));
+        }
+        dump
+    }
+
+    /// Copy the center and zoom of the view to the clipboard, as text to be read.
+    pub fn copy_coordinates(&self) {
+        let coordinates = Coordinates {
+            center_point: self.center_point,
+            zoom: self.zoom,
+        }
+        .to_string();
+        match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(&coordinates)) {
+            Ok(()) => println!("Copied the coordinates to the clipboard: {}", coordinates),
+            Err(error) => eprintln!(
+                "{}, the coordinates are: {}",
+                Error::from(error),
+                coordinates
+            ),
+        }
+    }
+
+    /// Go to the coordinates or the view string in the clipboard.
+    pub fn paste(&mut self) {
+        let text = match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+            Ok(text) => text,
+            Err(error) => {
+                eprintln!("{}", Error::from(error));
+                return;
+            }
+        };
+        if let Ok(coordinates) = text.parse::<Coordinates>() {
+            self.animate_to(export::Camera {
+                center: coordinates.center_point,
+                zoom: coordinates.zoom,
+            });
+            println!("Pasted the coordinates: {}", coordinates);
+            return;
+        }
+        match text.parse::<SharedView>() {
+            Ok(view) => {
+                self.apply_shared_view(view);
+                println!("Pasted the view: {}", text.trim());
+            }
+            Err(error) => eprintln!("Nothing to go to in the clipboard: {}", error),
+        }
+    }
+
+    /// Ask for coordinates to go to on the terminal, read on a separate thread so the
+    /// window keeps responding in the meantime.
+    pub fn type_coordinates(&mut self) {
+        if self.typed_coordinates.is_some() {
+            println!("Still waiting for the coordinates on the terminal");
+            return;
+        }
+        println!(
+            "Type the coordinates to go to as `re <x> im <y> zoom <zoom>` or `<x> <y> <zoom>`:"
+        );
+        let (sender, receiver) = mpsc::channel();
+        let window = Arc::clone(&self.window);
+        std::thread::spawn(move || {
+            let mut line = String::new();
+            if std::io::stdin().read_line(&mut line).is_ok() && sender.send(line).is_ok() {
+                // The line is picked up by the next redraw
+                window.request_redraw();
+            }
+        });
+        self.typed_coordinates = Some(receiver);
+    }
+
+    /// Go to the coordinates typed into the terminal once they arrive.
+    fn receive_coordinates(&mut self) {
+        let Some(receiver) = self.typed_coordinates.as_ref() else {
+            return;
+        };
+        let line = match receiver.try_recv() {
+            Ok(line) => line,
+            Err(mpsc::TryRecvError::Empty) => return,
+            // The terminal is closed, there's nothing to read anymore
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.typed_coordinates = None;
+                return;
+            }
+        };
+        self.typed_coordinates = None;
+        match line.parse::<Coordinates>() {
+            Ok(coordinates) => {
+                self.remember_camera();
+                self.set_camera(export::Camera {
+                    center: coordinates.center_point,
+                    zoom: coordinates.zoom,
+                });
+                println!("Went to {}", coordinates);
+            }
+            Err(error) => eprintln!("{}, press K to try again", error),
+        }
+    }
+
+    /// How the current view is colored and sampled, for rendering it on the CPU.
+    pub fn style(&self) -> export::Style {
+        export::Style {
+            palette: self.palette.clone(),
+            equalize: self.equalize,
+            supersampling: self.supersampling as usize,
+            phase: self.palette_phase,
+            gamma: self.gamma,
+            y_axis: self.y_axis,
+            dither: self.dither,
+            mapping: self.color_mapping,
+        }
+    }
+
+    /// The current view, for rendering it on the CPU.
+    pub fn camera(&self) -> export::Camera {
+        export::Camera {
+            center: self.center_point,
+            zoom: self.zoom,
+        }
+    }
+
+    pub fn key_pressed(&mut self, key: Key<&str>) {
+        // Any key stops a glide, like any other input does
+        self.glide = None;
+        let Some(action) = keys::action(&key, self.modifiers) else {
+            return;
+        };
+        if self.paused && !matches!(action, Action::TogglePause | Action::ToggleHelp) {
+            println!("Paused, press Space to continue");
+            return;
+        }
+        match action {
+            Action::TogglePause => self.toggle_pause(),
+            Action::ToggleHelp => {
+                self.show_help = !self.show_help;
+                self.window.request_redraw();
+            }
+            Action::ResetAll => self.reset_all(),
+            Action::UndoCamera => self.undo_camera(),
+            Action::RedoCamera => self.redo_camera(),
+            Action::ClearMeasurement => {
+                self.measurement.clear();
+                self.window.request_redraw();
+            }
+            Action::SaveBookmark | Action::JumpToBookmark => {
+                let Key::Character(character) = key else {
+                    return;
+                };
+                let slot = keys::digit(character).unwrap_or_default();
+                if action == Action::SaveBookmark {
+                    self.save_bookmark(slot);
+                } else {
+                    self.jump_to_bookmark(slot);
+                }
+            }
+            Action::CopyCoordinates => self.copy_coordinates(),
+            Action::Paste => self.paste(),
+            Action::ResetCamera => self.reset_camera(false),
+            Action::ResetCameraInstantly => self.reset_camera(true),
+            Action::SaveCounts => self.save_counts(),
+            Action::SavePng => self.save_png(self.window.inner_size()),
+            Action::SaveHighResolutionPng => self.save_png(HIGH_RESOLUTION_EXPORT),
+            Action::ExportZoomGif => self.export_zoom_gif(),
+            Action::ToggleSettingsPanel => match self.ui.as_mut() {
+                Some(ui) => {
+                    ui.visible = !ui.visible;
+                    self.window.request_redraw();
+                }
+                None => println!("The settings panel needs a GPU"),
+            },
+            Action::NextEscapeNorm => {
+                self.escape_norm = self.escape_norm.next();
+                println!("Escape norm: {:?}", self.escape_norm);
+                self.window.request_redraw();
+            }
+            Action::HalveIterations => self.scale_max_iterations(0.5),
+            Action::DoubleIterations => self.scale_max_iterations(2.0),
+            Action::ToggleAdaptiveIterations => self.toggle_adaptive_iterations(),
+            Action::NextFractalKind => self.next_fractal_kind(),
+            Action::ToggleJulia => self.toggle_julia(),
+            Action::NextOrbitTrap => self.next_orbit_trap(),
+            Action::LowerPower => self.change_power(-1),
+            Action::RaisePower => self.change_power(1),
+            Action::PrintState => println!("{}", self.state_dump()),
+            Action::PrintCountStats => self.print_count_stats(),
+            Action::ReloadPalette => self.reload_palette(),
+            Action::ToggleEqualize => self.toggle_equalize(),
+            Action::ToggleFillInterior => self.toggle_fill_interior(),
+            Action::ToggleInteriorPeriods => self.toggle_interior_periods(),
+            Action::ToggleColorCycling => self.toggle_color_cycling(),
+            Action::ToggleGrid => {
+                self.show_grid = !self.show_grid;
+                self.window.request_redraw();
+            }
+            Action::NextGamma => self.next_gamma(),
+            Action::NextColorMapping => self.next_color_mapping(),
+            Action::ToggleYAxis => self.toggle_y_axis(),
+            Action::TypeCoordinates => self.type_coordinates(),
+            Action::ToggleDither => self.toggle_dither(),
+            Action::ToggleTwoUp => self.toggle_two_up(),
+            Action::NextSupersampling => self.next_supersampling(),
+            Action::SaveRenderConfig => self.save_render_config(),
+            Action::CopyView => self.copy_view(),
+            Action::ToggleHud => {
+                self.hud = !self.hud;
+                self.window.request_redraw();
+            }
+            Action::ToggleMeasuring => self.toggle_measuring(),
+            Action::ToggleMinimap => {
+                self.show_minimap = !self.show_minimap;
+                self.window.request_redraw();
+            }
+            Action::NextInitialZ => {
+                self.initial_z = self.initial_z.next();
+                println!("Initial z: {:?}", self.initial_z);
+                self.window.request_redraw();
+            }
+        }
+    }
+
+    /// Save the current view into a PNG at `resolution`, which doesn't have to match
+    /// the window's.
+    ///
+    /// The view is rendered again on the CPU, on a separate thread so the event loop
+    /// keeps running, which also keeps the settings panel out of the image.
+    pub fn save_png(&self, resolution: PhysicalSize<u32>) {
+        if resolution.width == 0 || resolution.height == 0 {
+            println!("Nothing to save in a zero sized window");
+            return;
+        }
+        let camera = self.camera();
+        let iteration = self.iteration();
+        let style = self.style();
+        let threads = self.threads;
+        std::thread::spawn(move || {
+            let filename = format!(
+                "mandelbrot_{}_{}_z{}_{}x{}.png",
+                camera.center.0, camera.center.1, camera.zoom, resolution.width, resolution.height
+            );
+            match export::export_png(
+                &filename,
+                camera,
+                iteration,
+                &style,
+                (resolution.width as usize, resolution.height as usize),
+                threads,
+            ) {
+                Ok(()) => println!("Saved the view to {}", filename),
+                Err(error) => eprintln!("Failed to save the view: {}", error),
+            }
+        });
+    }
+
+    /// Save the escape counts of the current view at the window's resolution into a
+    /// NumPy file, uncolored, with the view in a TOML file next to it.
+    ///
+    /// Like the PNGs, the counts are rendered again on the CPU on a separate thread.
+    pub fn save_counts(&self) {
+        let resolution = self.window.inner_size();
+        if resolution.width == 0 || resolution.height == 0 {
+            println!("Nothing to save in a zero sized window");
+            return;
+        }
+        let view = self.bookmark();
+        let y_axis = self.y_axis;
+        let threads = self.threads;
+        std::thread::spawn(move || {
+            let filename = format!(
+                "mandelbrot_{}_{}_z{}_{}x{}.npy",
+                view.center_point.0,
+                view.center_point.1,
+                view.zoom,
+                resolution.width,
+                resolution.height
+            );
+            match export::export_counts(
+                Path::new(&filename),
+                &view,
+                y_axis,
+                (resolution.width as usize, resolution.height as usize),
+                threads,
+            ) {
+                Ok(()) => println!("Saved the escape counts to {}", filename),
+                Err(error) => eprintln!("Failed to save the escape counts: {}", error),
+            }
+        });
+    }
+
+    /// Print how the escape counts of the view are distributed, those of the frame
+    /// already rendered on the CPU, or counts rendered for it on a separate thread when
+    /// the GPU renders.
+    pub fn print_count_stats(&self) {
+        let iteration = self.iteration();
+        let limit = iteration.limit;
+        if self.active_backend() == Backend::Cpu && self.stride == 1 {
+            if let Some(stats) = self.count_stats {
+                print_count_stats(&stats, limit);
+                return;
+            }
+        }
+        let resolution = self.window.inner_size();
+        if resolution.width == 0 || resolution.height == 0 {
+            println!("No escape counts in a zero sized window");
+            return;
+        }
+        let camera = self.camera();
+        let y_axis = self.y_axis;
+        let threads = self.threads;
+        std::thread::spawn(move || {
+            let mut counts = cpu::EscapeCounts::default();
+            export::render_counts(
+                &mut counts,
+                (resolution.width as usize, resolution.height as usize),
+                camera,
+                iteration,
+                y_axis,
+                threads,
+            );
+            print_count_stats(&counts.stats(limit), limit);
+        });
+    }
+
+    /// Export an animated GIF zooming from the default view into the current one.
+    ///
+    /// The rendering happens on a separate thread so the event loop keeps running.
+    pub fn export_zoom_gif(&self) {
+        let from = self.default_camera();
+        let iteration = self.iteration();
+        let style = self.style();
+        let to = self.camera();
+        let settings = export::GifSettings {
+            threads: self.threads,
+            ..export::GifSettings::default()
+        };
+        std::thread::spawn(move || {
+            let filename = format!(
+                "mandelbrot_zoom_{}_{}_z{}.gif",
+                to.center.0, to.center.1, to.zoom
+            );
+            match export::export_zoom_gif(&filename, from, to, iteration, &style, &settings) {
+                Ok(()) => println!("Saved zoom animation to {}", filename),
+                Err(error) => eprintln!("Failed to export zoom animation: {}", error),
+            }
+        });
+    }
+}
+
+/// The view of `config`, unless a shared `view` overrides it.
+fn headless_view(config: &Config, view: Option<SharedView>) -> Result<SharedView, Error> {
+    Ok(match view {
+        Some(view) => view,
+        None => SharedView {
+            bookmark: Bookmark {
+                center_point: config.center_point,
+                zoom: config.zoom,
+                max_iterations: config.max_iterations,
+                ..Bookmark::default()
+            },
+            palette: match config.palette.as_deref() {
+                Some(path) => Palette::load(path)?,
+                None => Palette::default(),
+            },
+        },
+    })
+}
+
+/// Print `stats` of counts rendered with the iteration `limit`, with a hint to raise it
+/// when it's likely too low.
+fn print_count_stats(stats: &cpu::CountStats, limit: u32) {
+    println!("--- escape counts, limit {} ---\n{}", limit, stats);
+    if stats.limit_too_low() {
+        println!("Most pixels escape close to the limit, raising it would show more detail");
+    }
+}
+
+/// Render a view on the CPU into the PNG `out`, without opening a window.
+///
+/// The view is the one of `config`, unless a shared `view` overrides it. A
+/// `render_config` overrides both, being rendered exactly as it is.
+fn render_headless(
+    config: &Config,
+    view: Option<SharedView>,
+    render_config: Option<RenderConfig>,
+    out: &Path,
+) -> Result<(), Error> {
+    if let Some(render_config) = render_config {
+        let params = render_config.params(config.threads)?;
+        export::export_png(
+            &out.to_string_lossy(),
+            params.camera,
+            params.iteration,
+            &params.style,
+            params.resolution,
+            cpu::thread_count(params.threads),
+        )?;
+        println!("Saved the render to {}", out.display());
+        return Ok(());
+    }
+    let view = headless_view(config, view)?;
+    let camera = export::Camera {
+        center: view.bookmark.center_point,
+        zoom: view.bookmark.zoom,
+    };
+    let style = export::Style {
+        palette: view.palette,
+        gamma: config.gamma,
+        y_axis: config.y_axis,
+        dither: config.dither,
+        ..export::Style::default()
+    };
+    export::export_png(
+        &out.to_string_lossy(),
+        camera,
+        view.bookmark.iteration(),
+        &style,
+        (config.width as usize, config.height as usize),
+        cpu::thread_count(config.threads),
+    )?;
+    println!("Saved the view to {}", out.display());
+    Ok(())
+}
+
+/// Render the escape counts of the view of the command line into the NumPy file `out`.
+fn render_counts_headless(
+    config: &Config,
+    view: Option<SharedView>,
+    out: &Path,
+) -> Result<(), Error> {
+    let view = headless_view(config, view)?;
+    export::export_counts(
+        out,
+        &view.bookmark,
+        config.y_axis,
+        (config.width as usize, config.height as usize),
+        cpu::thread_count(config.threads),
+    )?;
+    println!("Saved the escape counts to {}", out.display());
+    Ok(())
+}
+
+/// Render the zoom of `animation`, starting from the view of the command line.
+fn render_frames(
+    config: &Config,
+    view: Option<SharedView>,
+    animation: &Animation,
+) -> Result<(), Error> {
+    let view = headless_view(config, view)?;
+    let from = export::Camera {
+        center: view.bookmark.center_point,
+        zoom: view.bookmark.zoom,
+    };
+    let to = export::Camera {
+        center: (
+            animation.target_re.unwrap_or(from.center.0),
+            animation.target_im.unwrap_or(from.center.1),
+        ),
+        zoom: animation.target_zoom.unwrap_or(from.zoom * 1000.0),
+    };
+    let style = export::Style {
+        palette: view.palette,
+        gamma: config.gamma,
+        y_axis: config.y_axis,
+        dither: config.dither,
+        ..export::Style::default()
+    };
+    let settings = export::FrameSettings {
+        frames: animation.frames,
+        fps: animation.fps,
+        resolution: (config.width as usize, config.height as usize),
+        threads: cpu::thread_count(config.threads),
+    };
+    export::export_zoom_frames(
+        &animation.directory,
+        from,
+        to,
+        view.bookmark.iteration(),
+        &style,
+        &settings,
+    )?;
+    println!(
+        "Saved {} frames to {}",
+        animation.frames,
+        animation.directory.display()
+    );
+    Ok(())
+}
+
+/// Outline the box between `corners` in white, into the 0RGB `buffer` of `bounds`.
+fn draw_box(
+    buffer: &mut [u32],
+    bounds: (usize, usize),
+    corners: (PhysicalPosition<f64>, PhysicalPosition<f64>),
+) {
+    let clamp = |position: f64, bound: usize| (position.max(0.0) as usize).min(bound - 1);
+    let (left, right) = (
+        clamp(corners.0.x.min(corners.1.x), bounds.0),
+        clamp(corners.0.x.max(corners.1.x), bounds.0),
+    );
+    let (top, bottom) = (
+        clamp(corners.0.y.min(corners.1.y), bounds.1),
+        clamp(corners.0.y.max(corners.1.y), bounds.1),
+    );
+    for column in left..=right {
+        buffer[top * bounds.0 + column] = 0xFFFFFF;
+        buffer[bottom * bounds.0 + column] = 0xFFFFFF;
+    }
+    for row in top..=bottom {
+        buffer[row * bounds.0 + left] = 0xFFFFFF;
+        buffer[row * bounds.0 + right] = 0xFFFFFF;
     }
 }
 
@@ -56,7 +2392,42 @@ impl ApplicationHandler for App {
         // The Window should be created in this call, because the winit documentation states that this
         // is the only point which they could guarantee proper initialization on all supported platforms.
         // And since WebGPU heavily relies on the Window object, this is where that should be initialized as well.
-        self.app = Some(InnerApp::new(event_loop));
+        match InnerApp::new(
+            event_loop,
+            self.config.clone(),
+            self.view.take(),
+            self.session.take(),
+        ) {
+            Ok(app) => self.app = Some(app),
+            Err(error) => {
+                eprintln!("Failed to start: {}", error);
+                event_loop.exit();
+            }
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        // Keep redrawing while the camera is animating, at most at the frame rate cap,
+        // and wait for events otherwise.
+        match self.app.as_ref().filter(|app| app.animating()) {
+            Some(app) => match app.frame_interval {
+                Some(interval) => {
+                    let now = Instant::now();
+                    let next_frame = app.frame_start + interval;
+                    if now >= next_frame {
+                        app.window.request_redraw();
+                        event_loop.set_control_flow(ControlFlow::WaitUntil(now + interval));
+                    } else {
+                        event_loop.set_control_flow(ControlFlow::WaitUntil(next_frame));
+                    }
+                }
+                None => {
+                    event_loop.set_control_flow(ControlFlow::Poll);
+                    app.window.request_redraw();
+                }
+            },
+            None => event_loop.set_control_flow(ControlFlow::Wait),
+        }
     }
 
     fn window_event(
@@ -65,9 +2436,20 @@ impl ApplicationHandler for App {
         _window_id: winit::window::WindowId, // we only have one window
         event: winit::event::WindowEvent,
     ) {
+        if let Some(app) = self.app.as_mut() {
+            if let Some(ui) = app.ui.as_mut() {
+                if ui.on_window_event(&app.window, &event) {
+                    return;
+                }
+            }
+        }
+
         match event {
             WindowEvent::CloseRequested => {
                 println!("The close button was pressed; stopping");
+                if let Some(app) = self.app.as_ref() {
+                    app.save_session();
+                }
                 event_loop.exit();
             }
             WindowEvent::RedrawRequested => {
@@ -78,88 +2460,41 @@ impl ApplicationHandler for App {
                 // the program to gracefully handle redraws requested by the OS.
 
                 // Draw.
-                if let Some(app) = self.app.as_ref() {
-                    let frame = app
-                        .gpu
-                        .surface
-                        .get_current_texture()
-                        .expect("Failed to acquire next swap-chain texture.");
-
-                    let view = frame
-                        .texture
-                        .create_view(&wgpu::TextureViewDescriptor::default());
-
-                    // let upper_left_x = app.center_point.0 - app.window.;
-                    // const lower_right = vec2f(-1.0, 0.2);
-                    // const width = lower_right.x - upper_left.x;
-                    // const height = upper_left.y - lower_right.y;
-                    // const bounds = vec2f(1024.0, 768.0);
-                    println!("{:?}", app.window.inner_size());
-                    // adjusted resolution for the given dpi setting on given screen
-                    let window_resolution = app.window.inner_size();
-                    let scale = (2.6 / window_resolution.height as f32) * (1.0 / app.zoom);
-                    let width = window_resolution.width as f32 * scale;
-                    let height = window_resolution.height as f32 * scale;
-                    let top_left = (
-                        app.center_point.0 - (width / 2.0),
-                        app.center_point.1 + (height / 2.0),
-                    );
-
-                    app.gpu.queue.write_buffer(
-                        &app.gpu.uniform_buffer,
-                        0,
-                        &[
-                            top_left.0,
-                            top_left.1,
-                            width,
-                            height,
-                            window_resolution.width as f32,
-                            window_resolution.height as f32,
-                        ]
-                        .iter()
-                        .flat_map(|entry| entry.to_ne_bytes())
-                        .collect::<Vec<u8>>(),
-                    );
-
-                    let mut encoder =
-                        app.gpu
-                            .device
-                            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                                label: Some("encoder"),
-                            });
-                    {
-                        let mut render_pass =
-                            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                                label: Some("render_pass"),
-                                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                                    view: &view,
-                                    resolve_target: None,
-                                    ops: wgpu::Operations {
-                                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                                        store: wgpu::StoreOp::Store,
-                                    },
-                                })],
-                                depth_stencil_attachment: None,
-                                timestamp_writes: None,
-                                occlusion_query_set: None,
-                            });
-                        render_pass.set_pipeline(&app.gpu.render_pipeline);
-                        render_pass.set_bind_group(0, &app.gpu.bind_group, &[]);
-                        render_pass.draw(0..4, 0..1);
+                if let Some(app) = self.app.as_mut() {
+                    match app.redraw() {
+                        Ok(()) => (),
+                        // The surface doesn't match the window anymore, set it up again and retry.
+                        Err(Error::Surface(
+                            wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated,
+                        )) => {
+                            if let Some(gpu) = app.gpu.as_mut() {
+                                gpu.resize(app.window.inner_size());
+                            }
+                            app.window.request_redraw();
+                        }
+                        Err(Error::Surface(wgpu::SurfaceError::OutOfMemory)) => {
+                            eprintln!("Out of memory; stopping");
+                            event_loop.exit();
+                        }
+                        Err(error) => eprintln!("Skipping frame: {}", error),
                     }
-                    app.gpu.queue.submit(Some(encoder.finish()));
-
-                    frame.present();
-
-                    // Queue a RedrawRequested event.
-                    //
-                    // You only need to call this if you've determined that you need to redraw in
-                    // applications which do not always need to. Applications that redraw continuously
-                    // can render here instead.
-                    // self.window.as_ref().unwrap().request_redraw();
                 }
                 // else nothing to do yet
             }
+            WindowEvent::Resized(size) => {
+                if let Some(app) = self.app.as_mut() {
+                    app.resize(size);
+                }
+            }
+            // Everything renders in physical pixels, so a new scale factor only changes
+            // the size of the surfaces. The Resized event following it, if any, is
+            // handled the same way.
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                if let Some(app) = self.app.as_mut() {
+                    println!("Scale factor: {}", scale_factor);
+                    app.resize(app.window.inner_size());
+                }
+            }
             WindowEvent::Focused(focused) => {
                 if let Some(app) = self.app.as_mut() {
                     app.focused = focused;
@@ -168,6 +2503,36 @@ impl ApplicationHandler for App {
                         // when the Window looses focus, as it is impossible to
                         // catch the release event when the user clicked off.
                         app.left_mouse = ElementState::Released;
+                        app.click_start = None;
+                    }
+                }
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key,
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                if let Some(app) = self.app.as_mut() {
+                    app.key_pressed(logical_key.as_ref());
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                if let Some(app) = self.app.as_mut() {
+                    app.modifiers = modifiers.state();
+                }
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                if let Some(app) = self.app.as_mut() {
+                    app.cursor_position = Some(position);
+                    app.follow_click(position);
+                    // Follow the cursor with the selected box, and with the point under
+                    // it in the HUD
+                    if app.selection_start.is_some() || app.hud {
+                        app.window.request_redraw();
                     }
                 }
             }
@@ -179,6 +2544,9 @@ impl ApplicationHandler for App {
             WindowEvent::CursorLeft { device_id: _ } => {
                 if let Some(app) = self.app.as_mut() {
                     app.in_window = false;
+                    if app.hud {
+                        app.window.request_redraw();
+                    }
                 }
             }
             _ => (),
@@ -193,38 +2561,82 @@ impl ApplicationHandler for App {
     ) {
         match event {
             DeviceEvent::MouseWheel { delta } => {
-                if let Some(app) = self.app.as_mut() {
-                    if app.focused && app.in_window {
-                        println!("{:?} MouseWheel delta: {:?}", device_id, delta);
-                        match delta {
-                            winit::event::MouseScrollDelta::LineDelta(_, dy) => {
-                                app.zoom += dy / 10.0 as f32;
-                            }
-                            _ => panic!("Interface not yet supported"),
+                // Navigation is ignored while paused
+                if let Some(app) = self.app.as_mut().filter(|app| !app.paused) {
+                    if app.focused && app.in_window && !app.ui_wants_pointer() {
+                        if app.verbose {
+                            println!("{:?} MouseWheel delta: {:?}", device_id, delta);
                         }
-                        app.window.request_redraw();
+                        let lines = match delta {
+                            winit::event::MouseScrollDelta::LineDelta(_, dy) => dy as f64,
+                            // Touchpads and macOS scroll by physical pixels, which are
+                            // counted in logical ones so a swipe zooms as far on a high
+                            // DPI screen as on any other
+                            winit::event::MouseScrollDelta::PixelDelta(position) => {
+                                let scale_factor = app.window.scale_factor();
+                                position.to_logical::<f64>(scale_factor).y / app.pixels_per_line
+                            }
+                        };
+                        let zoom = app.target_camera().zoom + lines * app.zoom_per_line;
+                        app.zoom_towards_cursor(zoom);
                     }
                 }
             }
             DeviceEvent::MouseMotion { delta } => {
-                if let Some(app) = self.app.as_mut() {
-                    if app.focused && app.in_window && app.left_mouse == ElementState::Pressed {
-                        println!("{:?} MouseMotion delta: {:?}", device_id, delta);
-                        app.center_point = (
-                            app.center_point.0 + (delta.0 as f32 / 100.0),
-                            // invert y axis movement
-                            app.center_point.1 - (delta.1 as f32 / 100.0),
-                        );
-                        app.window.request_redraw();
+                if let Some(app) = self.app.as_mut().filter(|app| !app.paused) {
+                    // Holding shift scrubs through the Julia constants instead of panning.
+                    if app.modifiers.shift_key() && app.julia_c.is_some() {
+                        if app.focused && app.in_window {
+                            app.scrub_julia(delta);
+                        }
+                    } else if app.focused
+                        && app.in_window
+                        && app.left_mouse == ElementState::Pressed
+                        && app.selection_start.is_none()
+                        && !app.ui_wants_pointer()
+                    {
+                        if app.verbose {
+                            println!("{:?} MouseMotion delta: {:?}", device_id, delta);
+                        }
+                        app.drag(delta);
                     }
                 }
             }
             DeviceEvent::Button { button, state } => {
                 if let Some(app) = self.app.as_mut() {
                     if button == 0 {
+                        // A press on the minimap moves the view there instead of dragging
+                        let on_minimap = state == ElementState::Pressed
+                            && !app.paused
+                            && app.focused
+                            && app.in_window
+                            && app
+                                .cursor_position
+                                .is_some_and(|position| app.click_minimap(position));
+                        if on_minimap {
+                            return;
+                        }
+                        if !app.paused {
+                            match state {
+                                ElementState::Pressed => {
+                                    app.glide = None;
+                                    app.start_click();
+                                }
+                                ElementState::Released => {
+                                    app.start_glide();
+                                    app.end_click();
+                                }
+                            }
+                        }
                         app.left_mouse = state;
+                        app.drag_remembered = false;
+                        if !app.paused {
+                            app.select_box(state);
+                        }
+                    }
+                    if app.verbose {
+                        println!("{:?} {:?}", button, state);
                     }
-                    println!("{:?} {:?}", button, state);
                 }
             }
             _ => {}
@@ -232,143 +2644,286 @@ impl ApplicationHandler for App {
     }
 }
 
-struct Wgpu {
-    pub surface: Surface<'static>,
-    pub device: Device,
-    pub queue: Queue,
-    pub bind_group: BindGroup,
-    pub uniform_buffer: wgpu::Buffer,
-    pub render_pipeline: RenderPipeline,
-}
+const USAGE: &str = "\
+Usage: mandelbrot [options]
+       mandelbrot render [options] [--out <path>]
+       mandelbrot counts [options] [--out <path>]
+       mandelbrot frames [options] [--out <directory>] [animation options]
+       mandelbrot bench
 
-impl Wgpu {
-    pub async fn new(window: Arc<Window>) -> Self {
-        let instance = wgpu::Instance::default();
-        let window_size = window.inner_size();
-        let surface = instance.create_surface(window).unwrap();
-        // Request an adapter that can support our surface
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                force_fallback_adapter: false,
-                compatible_surface: Some(&surface),
-            })
-            .await
-            .expect("Failed to find an appropriate adapter");
-
-        // Create logical device and command queue
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    required_features: wgpu::Features::empty(),
-                    // Make sure we use the texture resolution limits from the adapter, so we can support images the size of the swapchain.
-                    required_limits: wgpu::Limits::downlevel_defaults()
-                        .using_resolution(adapter.limits()),
-                    memory_hints: wgpu::MemoryHints::Performance,
-                },
-                None,
-            )
-            .await
-            .expect("Failed to create device");
-        println!("Prepared device: {:?}", device);
-
-        // Configure surface
-        let config = surface
-            .get_default_config(&adapter, window_size.width, window_size.height)
-            .unwrap();
-        surface.configure(&device, &config);
-
-        // Load the shaders
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("shader"),
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl"))),
-        });
+Options:
+  --width <pixels>      Inner width of the window
+  --height <pixels>     Inner height of the window
+  --cpu                 Render on the CPU
+  --gpu                 Render on the GPU, falling back to the CPU without one
+  --power-preference <p> Which GPU to prefer, default, low-power or high-performance
+  --adapter <adapter>   GPU to render on, by index or a part of its name, listing
+                        them all when none matches
+  --iterations <n>      Iteration limit
+  --center-re <x>       Real part of the view center
+  --center-im <y>       Imaginary part of the view center
+  --zoom <zoom>         Zoom of the view
+  --palette <path>      GIMP palette file to color with
+  --view <string>       View string copied with the C key
+  --dither              Dither the colors against banding in gentle gradients
+  --verbose             Print the size and time of every frame, the time the GPU
+                        took where it can tell, and the mouse input
+  --static-title        Only show the name in the window title, without the renderer,
+                        zoom and frame rate
+  --threads <n>         Threads to render on with the CPU, all of them by default,
+                        1 renders everything on the main thread
+  --max-fps <n>         Frame rate animations redraw at, at most, 60 by default, 0
+                        for as fast as possible
+  --zoom-per-line <n>   How much scrolling by one line adds to the zoom, 0.1 by default
+  --pixels-per-line <n> How many logical pixels of a touchpad scroll count as one
+                        line, 40 by default
+  --min-zoom <zoom>     Zoom scrolling out stops above, 0 by default
+  --max-zoom <zoom>     Zoom scrolling and box zooming stop at, unlimited by default
 
-        // Uniform buffer
-        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("settings_uniform"),
-            size: 6 * size_of::<f32>() as u64,
-            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+The render command renders on the CPU into a PNG without opening a window, with
+--width and --height being the size of the image:
+  --out <path>          The PNG to write, mandelbrot.png by default
+  --config <path>       Render configuration JSON saved with Shift+C, rendered exactly
+                        as it is, its own size and settings overriding the others
 
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("Bind group"),
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-        });
+The counts command renders the escape counts of the view on the CPU, uncolored, into
+a NumPy file of float32 that is NaN inside the set, with the view in a TOML file of
+the same name next to it:
+  --out <path>          The file to write, mandelbrot.npy by default
 
-        // Create bind group
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Settings"),
-            layout: &bind_group_layout,
-            entries: &[BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::Buffer(BufferBinding {
-                    buffer: &uniform_buffer,
-                    offset: 0,
-                    size: None, // use whole buffer
-                }),
-            }],
-        });
+The frames command renders a zoom from the view into numbered PNGs, also on the CPU:
+  --out <directory>     The directory to write, frames by default
+  --to-re <x>           Real part of the final view center, the starting one by default
+  --to-im <y>           Imaginary part of the final view center
+  --to-zoom <zoom>      Zoom of the final view, 1000 times the starting one by default
+  --frames <n>          Number of frames, 300 by default
+  --fps <n>             Frame rate written into the file names, 30 by default
 
-        // Pipeline
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("pipeline_layout"),
-            bind_group_layouts: &[&bind_group_layout],
-            push_constant_ranges: &[],
-        });
+The bench command times the CPU parallelization strategies against each other, and
+the CPU against the GPU at a few resolutions and iteration limits.";
 
-        let swapchain_capabilities = surface.get_capabilities(&adapter);
-        let swapchain_format = swapchain_capabilities.formats[0];
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("render_pipeline_descriptor"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[],
-                compilation_options: Default::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(swapchain_format.into())],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleStrip,
-                ..wgpu::PrimitiveState::default()
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-            cache: None, // on some platforms it may be good to use such a cache to reduce shader compilation times, otherwise it is handled by most
-        });
+/// What the command line asks for.
+enum Command {
+    Run,
+    /// Render into the PNG at the path without a window.
+    Render(PathBuf),
+    /// Render the escape counts into the NumPy file at the path without a window.
+    Counts(PathBuf),
+    /// Render a zoom into numbered PNGs without a window.
+    Frames(Animation),
+    Bench,
+}
+
+/// A zoom from the view of the command line to a target view.
+struct Animation {
+    directory: PathBuf,
+    target_re: Option<f64>,
+    target_im: Option<f64>,
+    target_zoom: Option<f64>,
+    frames: usize,
+    fps: u32,
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        let settings = export::FrameSettings::default();
+        Animation {
+            directory: PathBuf::from("frames"),
+            target_re: None,
+            target_im: None,
+            target_zoom: None,
+            frames: settings.frames,
+            fps: settings.fps,
+        }
+    }
+}
+
+/// Apply the command line `args` on top of the settings from the config file in `app`.
+fn parse_args(mut args: impl Iterator<Item = String>, app: &mut App) -> Result<Command, Error> {
+    let mut command = Command::Run;
+    let mut out = None;
+    let mut animation = Animation::default();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| Error::Argument(format!("{} needs a value", arg)))
+        };
+        match arg.as_str() {
+            "bench" => command = Command::Bench,
+            "render" => command = Command::Render(PathBuf::new()),
+            "counts" => command = Command::Counts(PathBuf::new()),
+            "frames" => command = Command::Frames(Animation::default()),
+            "--out" => out = Some(PathBuf::from(value()?)),
+            "--width" => app.config.width = parse_positive(&arg, value()?)?,
+            "--height" => app.config.height = parse_positive(&arg, value()?)?,
+            "--cpu" => app.config.backend = Backend::Cpu,
+            "--gpu" => app.config.backend = Backend::Gpu,
+            "--power-preference" => app.config.power_preference = value()?.parse()?,
+            "--adapter" => app.config.adapter = Some(value()?),
+            "--iterations" => {
+                let iterations = parse_value(&arg, value()?)?;
+                if !ITERATION_LIMITS.contains(&iterations) {
+                    return Err(Error::Argument(format!(
+                        "--iterations must be between {} and {}",
+                        ITERATION_LIMITS.start(),
+                        ITERATION_LIMITS.end()
+                    )));
+                }
+                app.config.max_iterations = iterations;
+            }
+            "--center-re" => app.config.center_point.0 = parse_value(&arg, value()?)?,
+            "--center-im" => app.config.center_point.1 = parse_value(&arg, value()?)?,
+            "--zoom" => app.config.zoom = parse_positive(&arg, value()?)?,
+            "--palette" => app.config.palette = Some(PathBuf::from(value()?)),
+            "--view" => app.view = Some(value()?.parse()?),
+            "--config" => app.render_config = Some(RenderConfig::load(Path::new(&value()?))?),
+            "--verbose" => app.config.verbose = true,
+            "--dither" => app.config.dither = true,
+            "--static-title" => app.config.live_title = false,
+            "--threads" => app.config.threads = parse_value(&arg, value()?)?,
+            "--max-fps" => {
+                let fps: f64 = parse_value(&arg, value()?)?;
+                if !(fps.is_finite() && fps >= 0.0) {
+                    return Err(Error::Argument("--max-fps must not be negative".into()));
+                }
+                app.config.max_fps = fps;
+            }
+            "--zoom-per-line" => app.config.zoom_per_line = parse_positive(&arg, value()?)?,
+            "--pixels-per-line" => app.config.pixels_per_line = parse_positive(&arg, value()?)?,
+            "--min-zoom" => {
+                let zoom: f64 = parse_value(&arg, value()?)?;
+                // 0 itself is allowed, as the minimum is exclusive
+                if !(zoom.is_finite() && zoom >= 0.0) {
+                    return Err(Error::Argument("--min-zoom must not be negative".into()));
+                }
+                app.config.min_zoom = zoom;
+            }
+            "--max-zoom" => app.config.max_zoom = parse_positive(&arg, value()?)?,
+            "--to-re" => animation.target_re = Some(parse_value(&arg, value()?)?),
+            "--to-im" => animation.target_im = Some(parse_value(&arg, value()?)?),
+            "--to-zoom" => animation.target_zoom = Some(parse_positive(&arg, value()?)?),
+            "--frames" => {
+                animation.frames = parse_value(&arg, value()?)?;
+                if animation.frames == 0 {
+                    return Err(Error::Argument("--frames must be positive".into()));
+                }
+            }
+            "--fps" => animation.fps = parse_value(&arg, value()?)?,
+            _ => return Err(Error::Argument(format!("unknown argument {}", arg))),
+        }
+    }
+    if app.render_config.is_some() && !matches!(command, Command::Render(_)) {
+        return Err(Error::Argument(
+            "--config only works with the render command".into(),
+        ));
+    }
+    match &mut command {
+        Command::Render(path) => *path = out.unwrap_or_else(|| PathBuf::from("mandelbrot.png")),
+        Command::Counts(path) => *path = out.unwrap_or_else(|| PathBuf::from("mandelbrot.npy")),
+        Command::Frames(frames) => {
+            if let Some(directory) = out {
+                animation.directory = directory;
+            }
+            *frames = animation;
+        }
+        Command::Run | Command::Bench => (),
+    }
+    Ok(command)
+}
 
-        Wgpu {
-            surface,
-            device,
-            queue,
-            bind_group,
-            uniform_buffer,
-            render_pipeline,
+/// Take the settings of the last `session` in place of the config file's.
+fn restore_session(app: &mut App, session: Session) {
+    let view = &session.view;
+    let center_finite = view.center_point.0.is_finite() && view.center_point.1.is_finite();
+    let valid = center_finite
+        && view.zoom.is_finite()
+        && view.zoom > 0.0
+        && ITERATION_LIMITS.contains(&view.max_iterations)
+        && POWER_LIMITS.contains(&view.power);
+    if !valid {
+        eprintln!("Starting without the last session, its view is invalid");
+        return;
+    }
+    app.config.backend = session.backend;
+    // A palette file gone since would keep the window from opening
+    match session.palette {
+        Some(path) if Palette::load(&path).is_err() => {
+            eprintln!("Not restoring the palette {}", path.display())
         }
+        palette => app.config.palette = palette,
     }
+    app.config.center_point = session.view.center_point;
+    app.config.zoom = session.view.zoom;
+    app.config.max_iterations = session.view.max_iterations;
+    app.session = Some(session.view);
+}
+
+fn parse_value<T: std::str::FromStr>(name: &str, value: String) -> Result<T, Error> {
+    value
+        .parse()
+        .map_err(|_| Error::Argument(format!("invalid value {} for {}", value, name)))
 }
 
-fn main() {
-    let event_loop = EventLoop::new().unwrap();
+fn parse_positive(name: &str, value: String) -> Result<f64, Error> {
+    let parsed: f64 = parse_value(name, value)?;
+    if !(parsed.is_finite() && parsed > 0.0) {
+        return Err(Error::Argument(format!("{} must be positive", name)));
+    }
+    Ok(parsed)
+}
+
+fn main() -> Result<(), Error> {
+    let mut app = App {
+        config: Config::load()?,
+        ..App::default()
+    };
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match parse_args(args.iter().cloned(), &mut app) {
+        Ok(command) => command,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            std::process::exit(2);
+        }
+    };
+    // The window starts where the last one was closed, the command line being applied
+    // again on top of the session to override it. Rendering without a window ignores
+    // the session, so it gives the same result every time.
+    if let Command::Run = command {
+        match Session::path().and_then(|path| Session::load(&path)) {
+            Ok(Some(session)) => {
+                restore_session(&mut app, session);
+                parse_args(args.into_iter(), &mut app)?;
+            }
+            Ok(None) => (),
+            Err(error) => eprintln!("Starting without the last session: {}", error),
+        }
+    }
+    // The tiles of the CPU renderer are rendered on the global pool, so it has to be
+    // limited along with everything else.
+    if app.config.threads != 0 {
+        if let Err(error) = rayon::ThreadPoolBuilder::new()
+            .num_threads(app.config.threads)
+            .build_global()
+        {
+            eprintln!("Failed to limit the number of threads: {}", error);
+        }
+    }
+    match command {
+        Command::Run => (),
+        Command::Render(out) => {
+            return render_headless(&app.config, app.view.take(), app.render_config.take(), &out)
+        }
+        Command::Counts(out) => return render_counts_headless(&app.config, app.view.take(), &out),
+        Command::Frames(animation) => {
+            return render_frames(&app.config, app.view.take(), &animation)
+        }
+        Command::Bench => {
+            bench::run(app.config.threads, &app.config.adapter_choice());
+            return Ok(());
+        }
+    }
+
+    let event_loop = EventLoop::new()?;
     // ControlFlow::Poll continuously runs the event loop, even if the OS hasn't
     // dispatched any events. This is ideal for games and similar applications.
     // event_loop.set_control_flow(ControlFlow::Poll);
@@ -377,6 +2932,6 @@ fn main() {
     // input, and uses significantly less power/CPU time than ControlFlow::Poll.
     event_loop.set_control_flow(ControlFlow::Wait);
 
-    let mut app = App::default();
-    let _ = event_loop.run_app(&mut app);
+    event_loop.run_app(&mut app)?;
+    Ok(())
 }
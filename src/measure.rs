@@ -0,0 +1,119 @@
+//! Measuring the distance between two points of the complex plane, placed by clicking
+//! them while measuring, toggled with Shift+M.
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+use mandelbrot::{point_to_pixel, YAxis};
+
+// The half length of the arms of the crosses marking the endpoints, at a scale factor of
+// 1.0
+const MARKER_SIZE: f64 = 4.0;
+
+/// The endpoints placed so far, kept in the complex plane so they stay on the same
+/// points while navigating.
+#[derive(Debug, Default)]
+pub struct Measurement {
+    // At most two, the next click after the second starting over
+    points: Vec<(f64, f64)>,
+}
+
+impl Measurement {
+    /// Place the next endpoint at `point`, or forget both once they are placed already.
+    pub fn click(&mut self, point: (f64, f64)) {
+        if self.points.len() == 2 {
+            self.points.clear();
+        } else {
+            self.points.push(point);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    /// Where the endpoints are in a view centered on `center_point`, in physical pixels.
+    pub fn pixels(
+        &self,
+        center_point: (f64, f64),
+        zoom: f64,
+        window_resolution: PhysicalSize<u32>,
+        y_axis: YAxis,
+    ) -> Vec<PhysicalPosition<f64>> {
+        self.points
+            .iter()
+            .map(|&point| point_to_pixel(center_point, zoom, window_resolution, y_axis, point))
+            .collect()
+    }
+
+    /// The distance between the endpoints in the complex plane and in pixels of the view
+    /// centered on `center_point`, one per line, None until both are placed.
+    pub fn text(
+        &self,
+        center_point: (f64, f64),
+        zoom: f64,
+        window_resolution: PhysicalSize<u32>,
+        y_axis: YAxis,
+    ) -> Option<String> {
+        let [from, to] = self.points[..] else {
+            return None;
+        };
+        let distance = (to.0 - from.0).hypot(to.1 - from.1);
+        let pixels = self.pixels(center_point, zoom, window_resolution, y_axis);
+        let pixel_distance = (pixels[1].x - pixels[0].x).hypot(pixels[1].y - pixels[0].y);
+        // Plain decimals stop being readable far from 1, where the exponent takes over
+        let distance = if distance == 0.0 || (1.0e-3..1.0e3).contains(&distance) {
+            format!("{:.6}", distance)
+        } else {
+            format!("{:.4e}", distance)
+        };
+        Some(format!("dist {}\npx {:.1}", distance, pixel_distance))
+    }
+}
+
+/// Draw the endpoints at `pixels` as crosses into the 0RGB `buffer` of `bounds`, with a
+/// line between them once both are placed.
+pub fn draw(
+    buffer: &mut [u32],
+    bounds: (usize, usize),
+    pixels: &[PhysicalPosition<f64>],
+    scale_factor: f64,
+) {
+    let size = MARKER_SIZE * scale_factor;
+    for pixel in pixels {
+        draw_line(
+            buffer,
+            bounds,
+            (pixel.x - size, pixel.y),
+            (pixel.x + size, pixel.y),
+        );
+        draw_line(
+            buffer,
+            bounds,
+            (pixel.x, pixel.y - size),
+            (pixel.x, pixel.y + size),
+        );
+    }
+    if let [from, to] = pixels {
+        draw_line(buffer, bounds, (from.x, from.y), (to.x, to.y));
+    }
+}
+
+/// Draw a white line from `from` to `to`, leaving out the pixels outside of `bounds`.
+fn draw_line(buffer: &mut [u32], bounds: (usize, usize), from: (f64, f64), to: (f64, f64)) {
+    // A pixel for every step along the longer side, at most as many as fit the window
+    let steps = (to.0 - from.0)
+        .abs()
+        .max((to.1 - from.1).abs())
+        .min((bounds.0 + bounds.1) as f64)
+        .ceil()
+        .max(1.0) as usize;
+    for step in 0..=steps {
+        let t = step as f64 / steps as f64;
+        let (x, y) = (
+            (from.0 + t * (to.0 - from.0)).round(),
+            (from.1 + t * (to.1 - from.1)).round(),
+        );
+        if (0.0..bounds.0 as f64).contains(&x) && (0.0..bounds.1 as f64).contains(&y) {
+            buffer[y as usize * bounds.0 + x as usize] = 0xFFFFFF;
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! A small overview of the whole Mandelbrot set in the bottom right corner of the window,
+//! marking the part of it in view, for keeping track of where a deep zoom is.
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+use mandelbrot::cpu::Iteration;
+use mandelbrot::export::{Camera, Style};
+use mandelbrot::{pixel_to_point, point_to_pixel, RenderParams, YAxis};
+
+// The size of the overview and its distance from the corner of the window at a scale
+// factor of 1.0
+const WIDTH: f64 = 160.0;
+const HEIGHT: f64 = 120.0;
+const MARGIN: f64 = 8.0;
+// The overview is tiny, so a low limit shows as much detail as it has room for
+const ITERATIONS: u32 = 64;
+// The view is marked at least this large, so it stays visible once it's smaller than
+// a pixel of the overview
+const MARKER_SIZE: f64 = 5.0;
+
+/// Where the overview and the view in it are, in physical pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    /// The top left corner of the overview in the window.
+    pub origin: PhysicalPosition<f64>,
+    pub size: PhysicalSize<u32>,
+    /// The opposite corners of the view's rectangle within the overview, relative to
+    /// `origin` and kept inside of it.
+    pub marker: (PhysicalPosition<f64>, PhysicalPosition<f64>),
+    /// The overview points the imaginary axis the same way as the view.
+    pub y_axis: YAxis,
+}
+
+impl Layout {
+    /// The overview in a window of `window_resolution`, marking the view centered on
+    /// `center_point` at `zoom`. None when the window is too small to fit it.
+    pub fn new(
+        window_resolution: PhysicalSize<u32>,
+        scale_factor: f64,
+        center_point: (f64, f64),
+        zoom: f64,
+        y_axis: YAxis,
+    ) -> Option<Self> {
+        let size = PhysicalSize::new(
+            (WIDTH * scale_factor).round() as u32,
+            (HEIGHT * scale_factor).round() as u32,
+        );
+        let margin = (MARGIN * scale_factor).round() as u32;
+        if window_resolution.width < size.width + 2 * margin
+            || window_resolution.height < size.height + 2 * margin
+        {
+            return None;
+        }
+        let origin = PhysicalPosition::new(
+            (window_resolution.width - size.width - margin) as f64,
+            (window_resolution.height - size.height - margin) as f64,
+        );
+
+        // The corners of the view, mapped into the overview
+        let corner = |pixel: (f64, f64)| {
+            let point = pixel_to_point(
+                center_point,
+                zoom,
+                window_resolution,
+                y_axis,
+                PhysicalPosition::new(pixel.0, pixel.1),
+            );
+            point_to_pixel(OVERVIEW.center, OVERVIEW.zoom, size, y_axis, point)
+        };
+        let top_left = corner((0.0, 0.0));
+        let bottom_right = corner((
+            window_resolution.width as f64,
+            window_resolution.height as f64,
+        ));
+        let middle = (
+            (top_left.x + bottom_right.x) / 2.0,
+            (top_left.y + bottom_right.y) / 2.0,
+        );
+        let half = (
+            ((bottom_right.x - top_left.x) / 2.0).max(MARKER_SIZE / 2.0),
+            ((bottom_right.y - top_left.y) / 2.0).max(MARKER_SIZE / 2.0),
+        );
+        let clamp = |position: f64, bound: u32| position.clamp(0.0, (bound - 1) as f64);
+        let marker = (
+            PhysicalPosition::new(
+                clamp(middle.0 - half.0, size.width),
+                clamp(middle.1 - half.1, size.height),
+            ),
+            PhysicalPosition::new(
+                clamp(middle.0 + half.0, size.width),
+                clamp(middle.1 + half.1, size.height),
+            ),
+        );
+        Some(Layout {
+            origin,
+            size,
+            marker,
+            y_axis,
+        })
+    }
+
+    /// The point of the complex plane under `position` of the window, None when it's
+    /// outside of the overview.
+    pub fn point_at(&self, position: PhysicalPosition<f64>) -> Option<(f64, f64)> {
+        let pixel = PhysicalPosition::new(position.x - self.origin.x, position.y - self.origin.y);
+        let inside = (0.0..self.size.width as f64).contains(&pixel.x)
+            && (0.0..self.size.height as f64).contains(&pixel.y);
+        inside.then(|| {
+            pixel_to_point(
+                OVERVIEW.center,
+                OVERVIEW.zoom,
+                self.size,
+                self.y_axis,
+                pixel,
+            )
+        })
+    }
+}
+
+/// The camera of the overview, framing the whole set.
+const OVERVIEW: Camera = Camera {
+    center: mandelbrot::DEFAULT_CENTER_POINT,
+    zoom: mandelbrot::DEFAULT_ZOOM,
+};
+
+/// The overview, rendered once for every size and orientation it's shown at, as the set
+/// never changes.
+#[derive(Debug, Default)]
+pub struct Minimap {
+    // The RGB pixels of the overview and the size and orientation they were rendered at
+    pixels: Vec<u8>,
+    size: PhysicalSize<u32>,
+    y_axis: YAxis,
+}
+
+impl Minimap {
+    /// The RGB pixels of the overview of `layout`, rendered on the CPU if the cached ones
+    /// are of a different size or orientation.
+    pub fn pixels(&mut self, layout: &Layout) -> &[u8] {
+        let (size, y_axis) = (layout.size, layout.y_axis);
+        if self.size != size || self.y_axis != y_axis || self.pixels.is_empty() {
+            self.pixels = mandelbrot::render(&RenderParams {
+                camera: OVERVIEW,
+                resolution: (size.width as usize, size.height as usize),
+                iteration: Iteration {
+                    limit: ITERATIONS,
+                    ..Iteration::default()
+                },
+                style: Style {
+                    y_axis,
+                    ..Style::default()
+                },
+                threads: 0,
+            });
+            self.size = size;
+            self.y_axis = y_axis;
+        }
+        &self.pixels
+    }
+
+    /// Draw the overview of `layout` into the 0RGB `buffer` of `bounds`, with the view
+    /// outlined in white.
+    pub fn draw(&mut self, buffer: &mut [u32], bounds: (usize, usize), layout: &Layout) {
+        let origin = (layout.origin.x as usize, layout.origin.y as usize);
+        let width = layout.size.width as usize;
+        let pixels = self.pixels(layout);
+        for (row, line) in pixels.chunks_exact(3 * width).enumerate() {
+            let start = (origin.1 + row) * bounds.0 + origin.0;
+            mandelbrot::pack_0rgb(line, &mut buffer[start..start + width]);
+        }
+
+        let (from, to) = layout.marker;
+        let (left, top) = (origin.0 + from.x as usize, origin.1 + from.y as usize);
+        let (right, bottom) = (origin.0 + to.x as usize, origin.1 + to.y as usize);
+        for column in left..=right {
+            buffer[top * bounds.0 + column] = 0xFFFFFF;
+            buffer[bottom * bounds.0 + column] = 0xFFFFFF;
+        }
+        for row in top..=bottom {
+            buffer[row * bounds.0 + left] = 0xFFFFFF;
+            buffer[row * bounds.0 + right] = 0xFFFFFF;
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Color gradients mapping the escape counts to colors, loadable from GIMP palette files.
+use std::path::Path;
+
+use crate::error::Error;
+
+/// The number of colors a palette is sampled into for the shader, which has to match
+/// the size of its `palette` array.
+pub const GPU_SAMPLES: usize = 256;
+
+/// A gradient of color stops, mapping positions from 0.0 to 1.0 to colors by
+/// interpolating linearly between the neighbouring stops.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    // Sorted by position, and never empty
+    stops: Vec<(f32, [u8; 3])>,
+}
+
+impl Default for Palette {
+    /// White fading into black, the grayscale look of the renderer without a palette.
+    fn default() -> Self {
+        Palette {
+            stops: vec![(0.0, [255, 255, 255]), (1.0, [0, 0, 0])],
+        }
+    }
+}
+
+impl Palette {
+    /// A palette of the `(position, color)` stops, in any order.
+    pub fn from_stops(mut stops: Vec<(f32, [u8; 3])>) -> Result<Self, Error> {
+        if stops.is_empty() {
+            return Err(Error::Palette("no colors".into()));
+        }
+        if stops
+            .iter()
+            .any(|(position, _)| !(0.0..=1.0).contains(position))
+        {
+            return Err(Error::Palette(
+                "stop positions must be between 0 and 1".into(),
+            ));
+        }
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(Palette { stops })
+    }
+
+    pub fn stops(&self) -> &[(f32, [u8; 3])] {
+        &self.stops
+    }
+
+    /// Load the colors of a GIMP palette (`.gpl`) file as evenly spaced stops.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        Palette::parse_gpl(&std::fs::read_to_string(path)?)
+    }
+
+    fn parse_gpl(text: &str) -> Result<Self, Error> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+        if lines.next() != Some("GIMP Palette") {
+            return Err(Error::Palette("missing the GIMP Palette header".into()));
+        }
+
+        let mut colors = Vec::new();
+        for line in lines {
+            if line.starts_with('#') || line.starts_with("Name:") || line.starts_with("Columns:") {
+                continue;
+            }
+            // The three channels, optionally followed by the name of the color
+            let channels: Vec<u8> = line
+                .split_whitespace()
+                .take(3)
+                .map(|channel| channel.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| Error::Palette(format!("invalid color: {}", line)))?;
+            let [r, g, b] = channels[..] else {
+                return Err(Error::Palette(format!("invalid color: {}", line)));
+            };
+            colors.push([r, g, b]);
+        }
+
+        let stops = match colors.len() {
+            0 => return Err(Error::Palette("no colors".into())),
+            1 => vec![(0.0, colors[0])],
+            len => colors
+                .into_iter()
+                .enumerate()
+                .map(|(index, color)| (index as f32 / (len - 1) as f32, color))
+                .collect(),
+        };
+        Ok(Palette { stops })
+    }
+
+    /// The color at `position`, clamped to the range from 0.0 to 1.0.
+    pub fn color(&self, position: f32) -> [u8; 3] {
+        self.exact_color(position)
+            .map(|channel| channel.round() as u8)
+    }
+
+    /// The color at `position` before rounding its channels to whole values.
+    fn exact_color(&self, position: f32) -> [f32; 3] {
+        let position = position.clamp(0.0, 1.0);
+        let next = self.stops.partition_point(|(stop, _)| *stop < position);
+        if next == 0 {
+            return self.stops[0].1.map(f32::from);
+        }
+        let Some(&(end, to)) = self.stops.get(next) else {
+            return self.stops[self.stops.len() - 1].1.map(f32::from);
+        };
+        let (start, from) = self.stops[next - 1];
+        let t = (position - start) / (end - start);
+        std::array::from_fn(|channel| {
+            from[channel] as f32 + (to[channel] as f32 - from[channel] as f32) * t
+        })
+    }
+
+    /// The color at `position` shifted by `phase`, wrapping around from the end of the
+    /// palette to its start, for cycling the colors through the image.
+    ///
+    /// A phase of 0.0 is the same as [`Palette::color`].
+    pub fn cycled_color(&self, position: f32, phase: f32) -> [u8; 3] {
+        if phase == 0.0 {
+            return self.color(position);
+        }
+        self.color((position + phase).rem_euclid(1.0))
+    }
+
+    /// The color of [`Palette::cycled_color`] with `threshold`, from -0.5 to 0.5, added to
+    /// its channels before they're rounded, which dithers gradients too gentle for whole
+    /// channel values into a pattern of the neighbouring values instead of bands.
+    pub fn dithered_color(&self, position: f32, phase: f32, threshold: f32) -> [u8; 3] {
+        let position = if phase == 0.0 {
+            position
+        } else {
+            (position + phase).rem_euclid(1.0)
+        };
+        self.exact_color(position)
+            .map(|channel| (channel + threshold).round().clamp(0.0, 255.0) as u8)
+    }
+
+    /// `samples` colors evenly spaced across the whole palette.
+    pub fn sample(&self, samples: usize) -> Vec<[u8; 3]> {
+        (0..samples)
+            .map(|sample| self.color(sample as f32 / (samples - 1).max(1) as f32))
+            .collect()
+    }
+}
+
+/// The threshold of the pixel at `x`, `y` in the ordered dithering of an 8 × 8 Bayer
+/// matrix, from -0.5 to 0.5. Being fixed, it dithers the same view the same way every time.
+///
+/// Mirrors `bayer_threshold` in shader.wgsl.
+pub fn bayer_threshold(x: usize, y: usize) -> f32 {
+    let (x, y) = (x & 7, y & 7);
+    let xor = x ^ y;
+    // The bits of x ^ y interleaved with those of y, in reverse
+    let index = ((xor & 1) << 5)
+        | ((y & 1) << 4)
+        | ((xor & 2) << 2)
+        | ((y & 2) << 1)
+        | ((xor & 4) >> 1)
+        | ((y & 4) >> 2);
+    (index as f32 + 0.5) / 64.0 - 0.5
+}
@@ -0,0 +1,152 @@
+//! Everything a render depends on in one JSON file, for rendering it again exactly as it
+//! was, on another machine or with the `--config` option.
+use std::path::Path;
+
+use num::Complex;
+use serde::{Deserialize, Serialize};
+use winit::dpi::PhysicalSize;
+
+use crate::cpu::{
+    ColorMapping, EscapeNorm, FractalKind, InitialZ, Iteration, OrbitTrap, TrapShape,
+    DEFAULT_BAILOUT_SQUARED, DEFAULT_ITERATION_LIMIT, DEFAULT_POWER, DEFAULT_TRAP_RADIUS,
+};
+use crate::error::Error;
+use crate::export::{Camera, Style};
+use crate::palette::Palette;
+use crate::{iteration_limit, view_is_valid, RenderParams, YAxis};
+
+/// A render, from the view down to how it's colored and sampled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+// Settings added since are missing from older files, and take their defaults
+#[serde(default, deny_unknown_fields)]
+pub struct RenderConfig {
+    pub center_point: (f64, f64),
+    pub zoom: f64,
+    /// The size of the render in pixels.
+    pub resolution: (usize, usize),
+    pub max_iterations: u32,
+    pub adaptive_iterations: bool,
+    pub bailout_squared: f64,
+    pub fractal_kind: FractalKind,
+    pub power: u32,
+    pub escape_norm: EscapeNorm,
+    pub initial_z: InitialZ,
+    pub julia_c: Option<(f64, f64)>,
+    pub orbit_trap: Option<TrapShape>,
+    pub trap_center: (f64, f64),
+    pub trap_radius: f64,
+    pub fill_interior: bool,
+    pub interior_periods: bool,
+    pub periodicity_checking: bool,
+    /// The stops of the palette, positions from 0.0 to 1.0 with their RGB colors.
+    pub palette: Vec<(f32, [u8; 3])>,
+    pub color_mapping: ColorMapping,
+    pub equalize: bool,
+    pub phase: f32,
+    pub gamma: f32,
+    pub supersampling: usize,
+    pub y_axis: YAxis,
+    pub dither: bool,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        let style = Style::default();
+        RenderConfig {
+            center_point: crate::DEFAULT_CENTER_POINT,
+            zoom: crate::DEFAULT_ZOOM,
+            resolution: (1024, 768),
+            max_iterations: DEFAULT_ITERATION_LIMIT,
+            adaptive_iterations: false,
+            bailout_squared: DEFAULT_BAILOUT_SQUARED,
+            fractal_kind: FractalKind::default(),
+            power: DEFAULT_POWER,
+            escape_norm: EscapeNorm::default(),
+            initial_z: InitialZ::default(),
+            julia_c: None,
+            orbit_trap: None,
+            trap_center: (0.0, 0.0),
+            trap_radius: DEFAULT_TRAP_RADIUS,
+            fill_interior: false,
+            interior_periods: false,
+            periodicity_checking: true,
+            palette: style.palette.stops().to_vec(),
+            color_mapping: style.mapping,
+            equalize: style.equalize,
+            phase: style.phase,
+            gamma: style.gamma,
+            supersampling: style.supersampling,
+            y_axis: style.y_axis,
+            dither: style.dither,
+        }
+    }
+}
+
+impl RenderConfig {
+    /// Load the render configuration from the JSON file `path`.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// What to render on `threads`, 0 meaning all of them, failing on settings that
+    /// can't be rendered, which a file edited by hand may well have.
+    pub fn params(&self, threads: usize) -> Result<RenderParams, Error> {
+        let invalid = |reason: &str| Err(Error::RenderConfig(reason.into()));
+        let (width, height) = self.resolution;
+        if width == 0 || height == 0 {
+            return invalid("the resolution is zero sized");
+        }
+        let window_resolution = PhysicalSize::new(width as u32, height as u32);
+        if !view_is_valid(self.center_point, self.zoom, window_resolution) {
+            return invalid("the view can't be rendered");
+        }
+        if self.power < 2 {
+            return invalid("the power must be at least 2");
+        }
+        if self.supersampling == 0 {
+            return invalid("the supersampling must be at least 1");
+        }
+        let palette = Palette::from_stops(self.palette.clone())?;
+        let complex = |(re, im): (f64, f64)| Complex::new(re, im);
+        Ok(RenderParams {
+            camera: Camera {
+                center: self.center_point,
+                zoom: self.zoom,
+            },
+            resolution: self.resolution,
+            iteration: Iteration {
+                kind: self.fractal_kind,
+                limit: iteration_limit(self.max_iterations, self.adaptive_iterations, self.zoom),
+                norm: self.escape_norm,
+                initial_z: self.initial_z,
+                bailout_squared: self.bailout_squared,
+                julia_c: self.julia_c.map(complex),
+                power: self.power,
+                trap: self.orbit_trap.map(|shape| OrbitTrap {
+                    shape,
+                    center: complex(self.trap_center),
+                    radius: self.trap_radius,
+                }),
+                fill_interior: self.fill_interior,
+                interior_periods: self.interior_periods,
+                periodicity_checking: self.periodicity_checking,
+            },
+            style: Style {
+                palette,
+                equalize: self.equalize,
+                supersampling: self.supersampling,
+                phase: self.phase,
+                gamma: self.gamma,
+                y_axis: self.y_axis,
+                dither: self.dither,
+                mapping: self.color_mapping,
+            },
+            threads,
+        })
+    }
+}
@@ -0,0 +1,43 @@
+//! The view and settings the window was closed with, kept in a TOML file next to the
+//! binary and restored on the next start, unless the command line says otherwise.
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use mandelbrot::bookmarks::Bookmark;
+use mandelbrot::error::Error;
+
+use crate::config::Backend;
+
+/// What the application was showing when it was last closed.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+// Settings added since are missing from older files, and take their defaults
+#[serde(default)]
+pub struct Session {
+    pub backend: Backend,
+    /// The GIMP palette file the view was colored with.
+    pub palette: Option<PathBuf>,
+    // Last, as TOML tables can't be followed by plain values
+    pub view: Bookmark,
+}
+
+impl Session {
+    /// The file the session is kept in, next to the binary.
+    pub fn path() -> Result<PathBuf, Error> {
+        Ok(std::env::current_exe()?.with_file_name("session.toml"))
+    }
+
+    /// Load the session from `path`, None if there was none saved yet.
+    pub fn load(path: &Path) -> Result<Option<Self>, Error> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Ok(Some(toml::from_str(&text)?)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
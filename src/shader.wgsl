@@ -21,38 +21,252 @@ fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) ve
     return position;
 }
 
+// Mirrors `Uniforms` in gpu.rs, which has to be changed along with it.
 struct Settings {
     upper_left: vec2f,
     width: f32,
     height: f32,
     window: vec2f,
+    // Where the tile being rendered starts in the whole render, in pixels
+    tile_offset: vec2f,
+    // 0: L2, 1: L∞, 2: L1
+    escape_norm: u32,
+    // 0: zero, 1: half of the point, 2: sine ripple
+    initial_z: u32,
+    max_iterations: u32,
+    // 1 to render the Julia set of `julia_c` instead of the Mandelbrot set
+    julia: u32,
+    julia_c: vec2f,
+    // 0: Mandelbrot, 1: Burning Ship
+    fractal_kind: u32,
+    bailout_squared: f32,
+    // Every pixel averages supersampling × supersampling samples
+    supersampling: u32,
+    // 0: no orbit trap, 1: point, 2: horizontal line, 3: circle
+    trap: u32,
+    trap_center: vec2f,
+    trap_radius: f32,
+    // z is raised to this power every iteration, 2 for the classic fractals
+    power: u32,
+    // 1 when the colors are encoded into sRGB as they are written
+    srgb_target: u32,
+    // How far the colors are cycled through the palette, from 0 to 1
+    palette_phase: f32,
+    // The counts scaled to 0 to 1 are raised to this power before looking up their color
+    gamma: f32,
+    // 1 to dither the colors with a Bayer matrix
+    dither: u32,
+    // How the counts are spread over the palette, 0: linear, 1: square root, 2: logarithmic
+    color_mapping: u32,
 };
 
 @group(0) @binding(0) var<uniform> settings: Settings;
+// Must match `palette::GPU_SAMPLES`
+const PALETTE_SAMPLES: u32 = 256u;
+// The palette sampled evenly from its start to its end into PALETTE_SAMPLES colors
+@group(0) @binding(1) var<uniform> palette: array<vec4f, 256>;
 
 @fragment
 fn fs_main(@builtin(position) position: vec4f) -> @location(0) vec4<f32> {
-    let point = vec2f(settings.upper_left.x + (position.x * settings.width / settings.window.x),
-        settings.upper_left.y - (position.y * settings.height / settings.window.y));
+    // The position is the pixel's center, the samples are spread evenly around it
+    let samples = settings.supersampling;
+    let pixel = position.xy + settings.tile_offset;
+    let corner = pixel - 0.5;
+    var color = vec4f(0.0);
+    for (var sample = 0u; sample < samples * samples; sample++) {
+        let offset = (vec2f(f32(sample % samples), f32(sample / samples)) + 0.5) / f32(samples);
+        color += sample_color(corner + offset);
+    }
+    color /= f32(samples * samples);
+    if settings.dither == 1u {
+        return vec4f(dither(color.rgb, pixel), color.a);
+    }
+    return color;
+}
+
+// Mirrors `palette::bayer_threshold`, for the pixel at the window `position`.
+fn bayer_threshold(position: vec2f) -> f32 {
+    let x = u32(position.x) & 7u;
+    let y = u32(position.y) & 7u;
+    let xor = x ^ y;
+    // The bits of x ^ y interleaved with those of y, in reverse
+    let index = ((xor & 1u) << 5u) | ((y & 1u) << 4u) | ((xor & 2u) << 2u) | ((y & 2u) << 1u) | ((xor & 4u) >> 1u) | ((y & 4u) >> 2u);
+    return (f32(index) + 0.5) / 64.0 - 0.5;
+}
+
+// `color` with the Bayer threshold of the pixel at `position` added to it, in steps of
+// the 8 bit channels it ends up in. An sRGB target encodes the colors after this, so the
+// threshold is added to the encoded channels there.
+fn dither(color: vec3f, position: vec2f) -> vec3f {
+    let threshold = bayer_threshold(position) / 255.0;
+    if settings.srgb_target == 1u {
+        return srgb_to_linear(clamp(linear_to_srgb(color) + threshold, vec3f(0.0), vec3f(1.0)));
+    }
+    return color + threshold;
+}
+
+fn linear_to_srgb(color: vec3f) -> vec3f {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3f(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3f(0.0031308));
+}
+
+fn srgb_to_linear(color: vec3f) -> vec3f {
+    let low = color / 12.92;
+    let high = pow((color + 0.055) / 1.055, vec3f(2.4));
+    return select(high, low, color <= vec3f(0.04045));
+}
+
+// The color of the point under the window position `pixel`. The height is negative while
+// the imaginary axis points down, which flips the image. Mirrors `gpu::shader_point`, and
+// `cpu::pixel_to_view` for the centers of the pixels.
+fn sample_color(pixel: vec2f) -> vec4f {
+    let point = vec2f(settings.upper_left.x + (pixel.x * settings.width / settings.window.x),
+        settings.upper_left.y - (pixel.y * settings.height / settings.window.y));
 
-    let escapes_in = escape_time(point, 255u);
-    let intensity: f32 = f32(255 - escapes_in) / 255.0;
-    return vec4f(intensity, intensity, intensity, 1.0);
+    let escapes_in = escape_time(point, settings.max_iterations);
+    if escapes_in >= f32(settings.max_iterations) {
+        return vec4f(0.0, 0.0, 0.0, 1.0);
+    }
+    var position = pow(mapped_position(escapes_in, f32(settings.max_iterations)), settings.gamma);
+    if settings.palette_phase != 0.0 {
+        position = fract(position + settings.palette_phase);
+    }
+    return palette_color(position);
+}
+
+// Mirrors `ColorMapping::position` in cpu.rs.
+fn mapped_position(count: f32, limit: f32) -> f32 {
+    if settings.color_mapping == 1u {
+        return sqrt(count / limit);
+    } else if settings.color_mapping == 2u {
+        return log(1.0 + count) / log(1.0 + limit);
+    }
+    return count / limit;
+}
+
+// Mirrors `Palette::color` in palette.rs, interpolating between the samples.
+fn palette_color(position: f32) -> vec4f {
+    let scaled = clamp(position, 0.0, 1.0) * f32(PALETTE_SAMPLES - 1u);
+    let index = u32(floor(scaled));
+    let next = min(index + 1u, PALETTE_SAMPLES - 1u);
+    return vec4f(mix(palette[index].rgb, palette[next].rgb, fract(scaled)), 1.0);
 }
 
 fn complex_square(z: vec2f) -> vec2f {
     return vec2f(pow(z.x, 2.0) - pow(z.y, 2.0), 2.0 * z.x * z.y);
 }
 
-fn escape_time(c: vec2f, limit: u32) -> u32 {
-    var z = vec2f(0.0, 0.0);
+fn complex_multiply(a: vec2f, b: vec2f) -> vec2f {
+    return vec2f(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+// Mirrors `FractalKind::step` in cpu.rs.
+fn step(z_start: vec2f, c: vec2f) -> vec2f {
+    var z = z_start;
+    if settings.fractal_kind == 1u {
+        z = abs(z);
+    }
+    if settings.power == 2u {
+        return complex_square(z) + c;
+    }
+    var raised = z;
+    for (var i = 1u; i < settings.power; i++) {
+        raised = complex_multiply(raised, z);
+    }
+    return raised + c;
+}
+
+fn escaped(z: vec2f) -> bool {
+    let bailout = sqrt(settings.bailout_squared);
+    if settings.escape_norm == 1u {
+        return max(abs(z.x), abs(z.y)) >= bailout;
+    } else if settings.escape_norm == 2u {
+        return (abs(z.x) + abs(z.y)) >= bailout;
+    }
+    let squared = z * z;
+    return (squared.x + squared.y) >= settings.bailout_squared;
+}
+
+fn initial_z(c: vec2f) -> vec2f {
+    if settings.initial_z == 1u {
+        return c / 2.0;
+    } else if settings.initial_z == 2u {
+        return 0.2 * sin(4.0 * vec2f(c.y, c.x));
+    }
+    return vec2f(0.0, 0.0);
+}
+
+// Mirrors `smooth_count` in cpu.rs.
+fn smooth_count(count: u32, z: vec2f) -> f32 {
+    let log_magnitude = max(log2(length(z)) / (0.5 * log2(settings.bailout_squared)), 1.0);
+    return max(f32(count) + 1.0 - log2(log_magnitude) / log2(f32(settings.power)), 0.0);
+}
+
+// Mirrors `known_period` in cpu.rs, without the periods: the main cardioid and the
+// period 2 bulb of the plain Mandelbrot set never escape, so they don't need iterating.
+fn known_interior(c: vec2f) -> bool {
+    if settings.fractal_kind != 0u || settings.power != 2u || settings.julia != 0u || settings.initial_z != 0u || settings.escape_norm != 0u {
+        return false;
+    }
+    let q = pow(c.x - 0.25, 2.0) + c.y * c.y;
+    let in_cardioid = q * (q + (c.x - 0.25)) <= 0.25 * c.y * c.y;
+    let in_bulb = pow(c.x + 1.0, 2.0) + c.y * c.y <= 1.0 / 16.0;
+    return in_cardioid || in_bulb;
+}
+
+fn trap_distance(z: vec2f) -> f32 {
+    if settings.trap == 2u {
+        return abs(z.y - settings.trap_center.y);
+    } else if settings.trap == 3u {
+        return abs(length(z - settings.trap_center) - settings.trap_radius);
+    }
+    return length(z - settings.trap_center);
+}
+
+// Mirrors `trapped_distance` in cpu.rs.
+fn trapped_distance(z_start: vec2f, c: vec2f, limit: u32) -> f32 {
+    var z = z_start;
+    // The largest f32, as `f64::MAX` is on the CPU, and where the orbits escaping right
+    // away start
+    var closest = 3.4e38;
+    if escaped(z) {
+        closest = trap_distance(z);
+    }
+    for (var i = 0u; i < limit; i++) {
+        if escaped(z) {
+            break;
+        }
+        z = step(z, c);
+        closest = min(closest, trap_distance(z));
+    }
+    return closest / (1.0 + closest) * f32(limit);
+}
+
+// The smoothed escape count, or the limit for points which don't escape. With an orbit
+// trap, the closest distance of the orbit to it instead.
+fn escape_time(point: vec2f, limit: u32) -> f32 {
+    if settings.trap != 0u {
+        if settings.julia == 1u {
+            return trapped_distance(point, settings.julia_c, limit);
+        }
+        return trapped_distance(initial_z(point), point, limit);
+    }
+    if known_interior(point) {
+        return f32(limit);
+    }
+    var z = initial_z(point);
+    var c = point;
+    if settings.julia == 1u {
+        z = point;
+        c = settings.julia_c;
+    }
 
     for (var i = 0u; i < limit; i++) {
-        let squared = z * z;
-        if (squared.x + squared.y) >= 4.0 {
-            return i;
+        if escaped(z) {
+            return smooth_count(i, z);
         }
-        z = complex_square(z) + c;
+        z = step(z, c);
     }
-    return 255u;
+    return f32(limit);
 }
\ No newline at end of file
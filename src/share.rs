@@ -0,0 +1,204 @@
+//! Views as short strings, for sending somebody the exact place being looked at.
+//!
+//! A view string is a `;` separated list of `key=value` fields, for example
+//! `re=-0.743643887037151;im=0.13182590420533;zoom=2500;iter=1024;kind=Mandelbrot`.
+//! Fields left at their defaults are left out, and missing ones take their defaults.
+//!
+//! Just the coordinates of a view can be shared as well, in a form meant to be read.
+use std::fmt;
+use std::str::FromStr;
+
+use crate::bookmarks::Bookmark;
+use crate::cpu::{EscapeNorm, FractalKind, InitialZ};
+use crate::error::Error;
+use crate::palette::Palette;
+use crate::{BAILOUT_SQUARED_LIMITS, ITERATION_LIMITS, POWER_LIMITS};
+
+/// A view and the palette it's colored with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedView {
+    pub bookmark: Bookmark,
+    pub palette: Palette,
+}
+
+impl fmt::Display for SharedView {
+    /// The coordinates are written with the shortest representation that parses back
+    /// to the same value, so the round trip is lossless.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let view = &self.bookmark;
+        let defaults = Bookmark::default();
+        write!(
+            f,
+            "re={};im={};zoom={};iter={};kind={:?}",
+            view.center_point.0,
+            view.center_point.1,
+            view.zoom,
+            view.max_iterations,
+            view.fractal_kind
+        )?;
+        if view.adaptive_iterations {
+            write!(f, ";adaptive=1")?;
+        }
+        if view.escape_norm != defaults.escape_norm {
+            write!(f, ";norm={:?}", view.escape_norm)?;
+        }
+        if view.initial_z != defaults.initial_z {
+            write!(f, ";z0={:?}", view.initial_z)?;
+        }
+        if view.bailout_squared != defaults.bailout_squared {
+            write!(f, ";bailout2={}", view.bailout_squared)?;
+        }
+        if view.power != defaults.power {
+            write!(f, ";power={}", view.power)?;
+        }
+        if let Some((re, im)) = view.julia_c {
+            write!(f, ";julia={},{}", re, im)?;
+        }
+        if self.palette != Palette::default() {
+            let stops: Vec<String> = self
+                .palette
+                .stops()
+                .iter()
+                .map(|(position, [r, g, b])| format!("{}:{:02x}{:02x}{:02x}", position, r, g, b))
+                .collect();
+            write!(f, ";palette={}", stops.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SharedView {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Error> {
+        let mut view = SharedView {
+            bookmark: Bookmark::default(),
+            palette: Palette::default(),
+        };
+        let bookmark = &mut view.bookmark;
+        for field in text.trim().split(';').filter(|field| !field.is_empty()) {
+            let invalid = || Error::View(format!("invalid field {}", field));
+            let (key, value) = field.split_once('=').ok_or_else(invalid)?;
+            match key {
+                "re" => bookmark.center_point.0 = value.parse().map_err(|_| invalid())?,
+                "im" => bookmark.center_point.1 = value.parse().map_err(|_| invalid())?,
+                "zoom" => bookmark.zoom = value.parse().map_err(|_| invalid())?,
+                "iter" => bookmark.max_iterations = value.parse().map_err(|_| invalid())?,
+                "adaptive" => bookmark.adaptive_iterations = value == "1",
+                "kind" => {
+                    bookmark.fractal_kind = match value {
+                        "Mandelbrot" => FractalKind::Mandelbrot,
+                        "BurningShip" => FractalKind::BurningShip,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "norm" => {
+                    bookmark.escape_norm = match value {
+                        "L2" => EscapeNorm::L2,
+                        "LInfinity" => EscapeNorm::LInfinity,
+                        "L1" => EscapeNorm::L1,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "z0" => {
+                    bookmark.initial_z = match value {
+                        "Zero" => InitialZ::Zero,
+                        "HalfPoint" => InitialZ::HalfPoint,
+                        "Ripple" => InitialZ::Ripple,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "bailout2" => bookmark.bailout_squared = value.parse().map_err(|_| invalid())?,
+                "power" => bookmark.power = value.parse().map_err(|_| invalid())?,
+                "julia" => {
+                    let (re, im) = value.split_once(',').ok_or_else(invalid)?;
+                    bookmark.julia_c = Some((
+                        re.parse().map_err(|_| invalid())?,
+                        im.parse().map_err(|_| invalid())?,
+                    ));
+                }
+                "palette" => {
+                    let stops = value
+                        .split(',')
+                        .map(|stop| {
+                            let (position, color) = stop.split_once(':')?;
+                            if color.len() != 6 {
+                                return None;
+                            }
+                            let color = u32::from_str_radix(color, 16).ok()?;
+                            let [_, r, g, b] = color.to_be_bytes();
+                            Some((position.parse().ok()?, [r, g, b]))
+                        })
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(invalid)?;
+                    view.palette = Palette::from_stops(stops)?;
+                }
+                _ => return Err(Error::View(format!("unknown field {}", key))),
+            }
+        }
+        // Whatever the fields were, the view has to be one the viewer can show
+        let out_of_range = |key| Err(Error::View(format!("{} is out of range", key)));
+        let (re, im) = bookmark.center_point;
+        if !(re.is_finite() && im.is_finite()) {
+            return Err(Error::View("the center isn't finite".into()));
+        }
+        if !(bookmark.zoom.is_finite() && bookmark.zoom > 0.0) {
+            return out_of_range("zoom");
+        }
+        if !ITERATION_LIMITS.contains(&bookmark.max_iterations) {
+            return out_of_range("iter");
+        }
+        if !POWER_LIMITS.contains(&bookmark.power) {
+            return out_of_range("power");
+        }
+        if !BAILOUT_SQUARED_LIMITS.contains(&bookmark.bailout_squared) {
+            return out_of_range("bailout2");
+        }
+        Ok(view)
+    }
+}
+
+/// The center and zoom of a view as plain text, for pasting into notes, for example
+/// `re -0.743643887037151 im 0.13182590420533 zoom 2500`.
+///
+/// When typed in, the labels can be left out, as in `-0.743643887037151 0.13182590420533 2500`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coordinates {
+    pub center_point: (f64, f64),
+    pub zoom: f64,
+}
+
+impl fmt::Display for Coordinates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "re {} im {} zoom {}",
+            self.center_point.0, self.center_point.1, self.zoom
+        )
+    }
+}
+
+impl FromStr for Coordinates {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Error> {
+        let invalid = || Error::View(format!("invalid coordinates {}", text.trim()));
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let (["re", re, "im", im, "zoom", zoom] | [re, im, zoom]) = words[..] else {
+            return Err(invalid());
+        };
+        let parse = |value: &str| value.parse::<f64>().map_err(|_| invalid());
+        let coordinates = Coordinates {
+            center_point: (parse(re)?, parse(im)?),
+            zoom: parse(zoom)?,
+        };
+        let (re, im) = coordinates.center_point;
+        if !(re.is_finite() && im.is_finite()) {
+            return Err(invalid());
+        }
+        if !(coordinates.zoom.is_finite() && coordinates.zoom > 0.0) {
+            return Err(invalid());
+        }
+        Ok(coordinates)
+    }
+}
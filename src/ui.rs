@@ -0,0 +1,567 @@
+use egui::ViewportId;
+use egui_wgpu::ScreenDescriptor;
+use num::Complex;
+use winit::{dpi::PhysicalPosition, event::WindowEvent, window::Window};
+
+use mandelbrot::cpu::{ColorMapping, EscapeNorm, FractalKind, InitialZ, TrapShape};
+
+use crate::grid::{self, Grid};
+use crate::minimap::Layout;
+
+/// The settings adjustable from the control panel.
+pub struct Controls<'a> {
+    pub center_point: &'a mut (f64, f64),
+    pub zoom: &'a mut f64,
+    pub fractal_kind: &'a mut FractalKind,
+    pub escape_norm: &'a mut EscapeNorm,
+    pub initial_z: &'a mut InitialZ,
+    pub max_iterations: &'a mut u32,
+    pub adaptive_iterations: &'a mut bool,
+    pub bailout_squared: &'a mut f64,
+    pub julia_c: &'a mut Option<Complex<f32>>,
+    pub power: &'a mut u32,
+    pub supersampling: &'a mut u32,
+    pub color_mapping: &'a mut ColorMapping,
+    pub gamma: &'a mut f32,
+    pub dither: &'a mut bool,
+    pub orbit_trap: &'a mut Option<TrapShape>,
+    pub trap_center: &'a mut (f64, f64),
+    pub trap_radius: &'a mut f64,
+}
+
+/// What is drawn on top of the fractal even while the panel is hidden.
+pub struct Overlay<'a> {
+    /// A warning shown at the top of the window.
+    pub banner: Option<&'a str>,
+    /// The opposite corners of the box zoom selection in progress, in physical pixels.
+    pub selection: Option<(PhysicalPosition<f64>, PhysicalPosition<f64>)>,
+    /// The status text, shown in the bottom left corner to stay clear of the panel.
+    pub hud: Option<&'a str>,
+    /// The keybindings, shown over the dimmed fractal.
+    pub help: Option<&'a str>,
+    /// The endpoints of the measurement placed so far, in physical pixels.
+    pub measurement: &'a [PhysicalPosition<f64>],
+    /// The axes and the labeled grid lines of the complex plane.
+    pub grid: Option<&'a Grid>,
+    /// The RGB pixels of the overview of the whole set, and where it and the view in it
+    /// are.
+    pub minimap: Option<(&'a [u8], Layout)>,
+    /// The RGB pixels of the left half of the window rendered on the CPU and their
+    /// width and height, shown over the GPU's with a divider next to them.
+    pub cpu_half: Option<(&'a [u8], (usize, usize))>,
+}
+
+/// A small egui panel drawn on top of the fractal, for adjusting the settings
+/// without having to remember the keybindings.
+pub struct Ui {
+    pub context: egui::Context,
+    state: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+    // The overview of the minimap, uploaded once for every size it's shown at
+    minimap_texture: Option<egui::TextureHandle>,
+    // The CPU half of the two-up view, uploaded again along with every change of the view
+    cpu_half_texture: Option<egui::TextureHandle>,
+    pub visible: bool,
+}
+
+impl Ui {
+    pub fn new(window: &Window, device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let context = egui::Context::default();
+        let state = egui_winit::State::new(
+            context.clone(),
+            ViewportId::ROOT,
+            window,
+            Some(window.scale_factor() as f32),
+            None,
+            None,
+        );
+        let renderer = egui_wgpu::Renderer::new(device, format, None, 1, false);
+
+        Ui {
+            context,
+            state,
+            renderer,
+            minimap_texture: None,
+            cpu_half_texture: None,
+            visible: true,
+        }
+    }
+
+    /// Feed a window event to egui, returning true if egui consumed it,
+    /// so it shouldn't be handled by the application as well.
+    pub fn on_window_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        let response = self.state.on_window_event(window, event);
+        if response.repaint && self.visible {
+            window.request_redraw();
+        }
+        self.visible && response.consumed
+    }
+
+    /// True while the pointer is over or interacting with the panel,
+    /// in which case mouse input shouldn't move the view.
+    pub fn wants_pointer(&self) -> bool {
+        self.visible && (self.context.is_pointer_over_area() || self.context.is_using_pointer())
+    }
+
+    /// Run the panel for one frame, updating `controls` from the widgets, along with the
+    /// `overlay`. The resulting shapes are kept until [`Ui::paint`] draws them.
+    pub fn run(
+        &mut self,
+        window: &Window,
+        mut controls: Controls,
+        overlay: Overlay,
+    ) -> egui::FullOutput {
+        let raw_input = self.state.take_egui_input(window);
+        let visible = self.visible;
+        let minimap = overlay.minimap.map(|(pixels, layout)| {
+            let size = [layout.size.width as usize, layout.size.height as usize];
+            let texture = match self.minimap_texture.take() {
+                Some(texture) if texture.size() == size => texture,
+                _ => self.context.load_texture(
+                    "minimap",
+                    egui::ColorImage::from_rgb(size, pixels),
+                    egui::TextureOptions::LINEAR,
+                ),
+            };
+            (self.minimap_texture.insert(texture).id(), layout)
+        });
+        let cpu_half_texture = self.cpu_half_texture.take();
+        let cpu_half = overlay.cpu_half.map(|(pixels, (width, height))| {
+            let image = egui::ColorImage::from_rgb([width, height], pixels);
+            // Nearest, so the pixels compare one to one with the GPU's
+            let options = egui::TextureOptions::NEAREST;
+            let texture = match cpu_half_texture {
+                Some(mut texture) => {
+                    texture.set(image, options);
+                    texture
+                }
+                None => self.context.load_texture("cpu_half", image, options),
+            };
+            (self.cpu_half_texture.insert(texture).id(), (width, height))
+        });
+        let full_output = self.context.run(raw_input, |context| {
+            // The background layer stacks these in the order they are drawn
+            if let Some((texture, size)) = cpu_half {
+                self::cpu_half(context, texture, size);
+            }
+            if let Some(grid) = overlay.grid {
+                self::grid(context, grid);
+            }
+            if visible {
+                panel(context, &mut controls);
+            }
+            if let Some(text) = overlay.banner {
+                warning_banner(context, text);
+            }
+            if let Some(corners) = overlay.selection {
+                selection_box(context, corners);
+            }
+            if !overlay.measurement.is_empty() {
+                measurement(context, overlay.measurement);
+            }
+            if let Some(text) = overlay.hud {
+                hud(context, text);
+            }
+            if let Some((texture, layout)) = minimap {
+                self::minimap(context, texture, layout);
+            }
+            if let Some(text) = overlay.help {
+                help(context, text);
+            }
+        });
+        self.state
+            .handle_platform_output(window, full_output.platform_output.clone());
+
+        let repaint = full_output
+            .viewport_output
+            .get(&ViewportId::ROOT)
+            .is_some_and(|viewport| viewport.repaint_delay.is_zero());
+        if repaint {
+            window.request_redraw();
+        }
+        full_output
+    }
+
+    /// Draw the output of [`Ui::run`] on top of what `view` already contains.
+    pub fn paint(
+        &mut self,
+        window: &Window,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        full_output: egui::FullOutput,
+    ) {
+        let window_resolution = window.inner_size();
+        let screen_descriptor = ScreenDescriptor {
+            size_in_pixels: [window_resolution.width, window_resolution.height],
+            pixels_per_point: full_output.pixels_per_point,
+        };
+        let primitives = self
+            .context
+            .tessellate(full_output.shapes, full_output.pixels_per_point);
+
+        for (id, image_delta) in &full_output.textures_delta.set {
+            self.renderer
+                .update_texture(device, queue, *id, image_delta);
+        }
+        self.renderer
+            .update_buffers(device, queue, encoder, &primitives, &screen_descriptor);
+
+        {
+            let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("ui_render_pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        // Keep the fractal underneath the panel
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.renderer.render(
+                &mut render_pass.forget_lifetime(),
+                &primitives,
+                &screen_descriptor,
+            );
+        }
+
+        for id in &full_output.textures_delta.free {
+            self.renderer.free_texture(id);
+        }
+    }
+}
+
+fn panel(context: &egui::Context, controls: &mut Controls) {
+    egui::Window::new("Settings")
+        .default_pos([10.0, 10.0])
+        .resizable(false)
+        .show(context, |ui| {
+            egui::Grid::new("settings_grid").show(ui, |ui| {
+                ui.label("Center re");
+                let speed = 0.01 / *controls.zoom;
+                ui.add(egui::DragValue::new(&mut controls.center_point.0).speed(speed));
+                ui.end_row();
+
+                ui.label("Center im");
+                ui.add(egui::DragValue::new(&mut controls.center_point.1).speed(speed));
+                ui.end_row();
+
+                ui.label("Zoom");
+                // The range only covers dragging, zooms past it from the keybindings, the
+                // command line or a bookmark are kept rather than clamped every frame
+                let mut zoom = *controls.zoom;
+                ui.add(
+                    egui::Slider::new(&mut zoom, 0.1..=100_000.0)
+                        .logarithmic(true)
+                        .clamping(egui::SliderClamping::Never),
+                );
+                // A zoom typed in that isn't positive would flip the view upside down
+                if zoom.is_finite() && zoom > 0.0 {
+                    *controls.zoom = zoom;
+                }
+                ui.end_row();
+
+                ui.label("Fractal");
+                egui::ComboBox::from_id_salt("fractal_kind")
+                    .selected_text(format!("{:?}", controls.fractal_kind))
+                    .show_ui(ui, |ui| {
+                        for kind in [FractalKind::Mandelbrot, FractalKind::BurningShip] {
+                            ui.selectable_value(controls.fractal_kind, kind, format!("{:?}", kind));
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Iterations");
+                ui.add(
+                    egui::Slider::new(controls.max_iterations, crate::ITERATION_LIMITS)
+                        .logarithmic(true),
+                );
+                ui.end_row();
+
+                ui.label("Adaptive iterations");
+                ui.checkbox(controls.adaptive_iterations, "")
+                    .on_hover_text("Grow the iteration limit as the view zooms in");
+                ui.end_row();
+
+                ui.label("Bailout radius²");
+                ui.add(
+                    egui::Slider::new(controls.bailout_squared, crate::BAILOUT_SQUARED_LIMITS)
+                        .logarithmic(true),
+                );
+                ui.end_row();
+
+                ui.label("Escape norm");
+                egui::ComboBox::from_id_salt("escape_norm")
+                    .selected_text(format!("{:?}", controls.escape_norm))
+                    .show_ui(ui, |ui| {
+                        for norm in [EscapeNorm::L2, EscapeNorm::LInfinity, EscapeNorm::L1] {
+                            ui.selectable_value(controls.escape_norm, norm, format!("{:?}", norm));
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Power");
+                ui.add(egui::Slider::new(controls.power, crate::POWER_LIMITS));
+                ui.end_row();
+
+                ui.label("Julia set");
+                let mut julia = controls.julia_c.is_some();
+                if ui.checkbox(&mut julia, "").changed() {
+                    let center = *controls.center_point;
+                    *controls.julia_c =
+                        julia.then(|| Complex::new(center.0 as f32, center.1 as f32));
+                }
+                ui.end_row();
+
+                if let Some(julia_c) = controls.julia_c.as_mut() {
+                    ui.label("Julia re");
+                    ui.add(egui::DragValue::new(&mut julia_c.re).speed(0.001));
+                    ui.end_row();
+
+                    ui.label("Julia im");
+                    ui.add(egui::DragValue::new(&mut julia_c.im).speed(0.001));
+                    ui.end_row();
+                }
+
+                ui.label("Orbit trap");
+                let trap_name = |trap: Option<TrapShape>| match trap {
+                    Some(shape) => format!("{:?}", shape),
+                    None => "Off".to_string(),
+                };
+                egui::ComboBox::from_id_salt("orbit_trap")
+                    .selected_text(trap_name(*controls.orbit_trap))
+                    .show_ui(ui, |ui| {
+                        let traps = std::iter::once(None).chain(TrapShape::ALL.map(Some));
+                        for trap in traps {
+                            ui.selectable_value(controls.orbit_trap, trap, trap_name(trap));
+                        }
+                    });
+                ui.end_row();
+
+                if let Some(shape) = *controls.orbit_trap {
+                    ui.label("Trap re");
+                    ui.add(egui::DragValue::new(&mut controls.trap_center.0).speed(0.001));
+                    ui.end_row();
+
+                    ui.label("Trap im");
+                    ui.add(egui::DragValue::new(&mut controls.trap_center.1).speed(0.001));
+                    ui.end_row();
+
+                    if shape == TrapShape::Circle {
+                        ui.label("Trap radius");
+                        ui.add(
+                            egui::DragValue::new(controls.trap_radius)
+                                .speed(0.001)
+                                .range(0.0..=4.0),
+                        );
+                        ui.end_row();
+                    }
+                }
+
+                ui.label("Supersampling");
+                ui.add(
+                    egui::Slider::new(controls.supersampling, 1..=crate::MAX_SUPERSAMPLING)
+                        .suffix("²"),
+                );
+                ui.end_row();
+
+                ui.label("Color mapping");
+                egui::ComboBox::from_id_salt("color_mapping")
+                    .selected_text(format!("{:?}", controls.color_mapping))
+                    .show_ui(ui, |ui| {
+                        let mappings = [
+                            ColorMapping::Linear,
+                            ColorMapping::SquareRoot,
+                            ColorMapping::Logarithmic,
+                        ];
+                        for mapping in mappings {
+                            ui.selectable_value(
+                                controls.color_mapping,
+                                mapping,
+                                format!("{:?}", mapping),
+                            );
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Gamma");
+                ui.add(egui::Slider::new(controls.gamma, crate::GAMMA_LIMITS));
+                ui.end_row();
+
+                ui.label("Dither");
+                ui.checkbox(controls.dither, "")
+                    .on_hover_text("Break up the bands of gentle gradients");
+                ui.end_row();
+
+                ui.label("Initial z");
+                egui::ComboBox::from_id_salt("initial_z")
+                    .selected_text(format!("{:?}", controls.initial_z))
+                    .show_ui(ui, |ui| {
+                        for initial_z in [InitialZ::Zero, InitialZ::HalfPoint, InitialZ::Ripple] {
+                            ui.selectable_value(
+                                controls.initial_z,
+                                initial_z,
+                                format!("{:?}", initial_z),
+                            );
+                        }
+                    });
+                ui.end_row();
+            });
+        });
+}
+
+fn selection_box(context: &egui::Context, corners: (PhysicalPosition<f64>, PhysicalPosition<f64>)) {
+    let scale = context.pixels_per_point();
+    let to_point = |corner: PhysicalPosition<f64>| {
+        egui::pos2(corner.x as f32 / scale, corner.y as f32 / scale)
+    };
+    let painter = context.layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("selection_box"),
+    ));
+    painter.rect_stroke(
+        egui::Rect::from_two_pos(to_point(corners.0), to_point(corners.1)),
+        0.0,
+        egui::Stroke::new(1.0, egui::Color32::WHITE),
+    );
+}
+
+fn measurement(context: &egui::Context, pixels: &[PhysicalPosition<f64>]) {
+    let scale = context.pixels_per_point();
+    let to_point =
+        |pixel: &PhysicalPosition<f64>| egui::pos2(pixel.x as f32 / scale, pixel.y as f32 / scale);
+    let painter = context.layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("measurement"),
+    ));
+    let stroke = egui::Stroke::new(1.0, egui::Color32::WHITE);
+    for pixel in pixels {
+        painter.circle_stroke(to_point(pixel), 4.0, stroke);
+    }
+    if let [from, to] = pixels {
+        painter.line_segment([to_point(from), to_point(to)], stroke);
+    }
+}
+
+fn minimap(context: &egui::Context, texture: egui::TextureId, layout: Layout) {
+    let scale = context.pixels_per_point();
+    let to_point = |position: PhysicalPosition<f64>| {
+        egui::pos2(
+            (layout.origin.x + position.x) as f32 / scale,
+            (layout.origin.y + position.y) as f32 / scale,
+        )
+    };
+    let painter = context.layer_painter(egui::LayerId::background());
+    let size = PhysicalPosition::new(layout.size.width as f64, layout.size.height as f64);
+    painter.image(
+        texture,
+        egui::Rect::from_two_pos(to_point(PhysicalPosition::new(0.0, 0.0)), to_point(size)),
+        egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+        egui::Color32::WHITE,
+    );
+    painter.rect_stroke(
+        egui::Rect::from_two_pos(to_point(layout.marker.0), to_point(layout.marker.1)),
+        0.0,
+        egui::Stroke::new(1.0, egui::Color32::WHITE),
+    );
+}
+
+fn cpu_half(context: &egui::Context, texture: egui::TextureId, size: (usize, usize)) {
+    let scale = context.pixels_per_point();
+    let right = size.0 as f32 / scale;
+    let bottom = size.1 as f32 / scale;
+    let painter = context.layer_painter(egui::LayerId::background());
+    painter.image(
+        texture,
+        egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(right, bottom)),
+        egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+        egui::Color32::WHITE,
+    );
+    painter.vline(
+        right,
+        0.0..=bottom,
+        egui::Stroke::new(2.0, egui::Color32::WHITE),
+    );
+}
+
+fn grid(context: &egui::Context, grid: &Grid) {
+    let scale = context.pixels_per_point();
+    let screen = context.screen_rect();
+    let offset = grid::LABEL_OFFSET as f32;
+    let painter = context.layer_painter(egui::LayerId::background());
+    let stroke = |value: f64| {
+        let color = if value == 0.0 {
+            egui::Color32::WHITE
+        } else {
+            egui::Color32::from_white_alpha(64)
+        };
+        egui::Stroke::new(1.0, color)
+    };
+    let label = |position: egui::Pos2, text: String| {
+        painter.text(
+            position,
+            egui::Align2::LEFT_TOP,
+            text,
+            egui::FontId::monospace(12.0),
+            egui::Color32::WHITE,
+        );
+    };
+    for &(re, x) in &grid.vertical {
+        let x = x as f32 / scale;
+        painter.vline(x, screen.y_range(), stroke(re));
+        label(egui::pos2(x + offset, offset), grid.label(re));
+    }
+    for &(im, y) in &grid.horizontal {
+        let y = y as f32 / scale;
+        painter.hline(screen.x_range(), y, stroke(im));
+        label(egui::pos2(offset, y + offset), grid.label(im));
+    }
+}
+
+fn hud(context: &egui::Context, text: &str) {
+    egui::Area::new(egui::Id::new("hud"))
+        .anchor(egui::Align2::LEFT_BOTTOM, [10.0, -10.0])
+        .interactable(false)
+        .show(context, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.label(egui::RichText::new(text).monospace());
+            });
+        });
+}
+
+fn help(context: &egui::Context, text: &str) {
+    // Dims everything of the background layer, the fractal, the grid and the CPU half
+    context
+        .layer_painter(egui::LayerId::background())
+        .rect_filled(
+            context.screen_rect(),
+            0.0,
+            egui::Color32::from_black_alpha(160),
+        );
+    egui::Area::new(egui::Id::new("help"))
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .order(egui::Order::Foreground)
+        .interactable(false)
+        .show(context, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.label(egui::RichText::new(text).monospace());
+            });
+        });
+}
+
+fn warning_banner(context: &egui::Context, text: &str) {
+    egui::Area::new(egui::Id::new("warning_banner"))
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 10.0])
+        .interactable(false)
+        .show(context, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.colored_label(egui::Color32::YELLOW, text);
+            });
+        });
+}
@@ -0,0 +1,179 @@
+//! The CPU and GPU renderers have to draw the same image of the same view.
+use mandelbrot::cpu::{Iteration, OrbitTrap, TrapShape};
+use mandelbrot::export::{Camera, Style};
+use mandelbrot::gpu::{AdapterChoice, Offscreen};
+use mandelbrot::{error::Error, RenderParams, YAxis};
+use num::Complex;
+use winit::dpi::PhysicalSize;
+
+const RESOLUTION: (usize, usize) = (160, 120);
+/// The largest difference of a color channel still considered the same color, allowing
+/// for the GPU interpolating between palette samples instead of the stops themselves.
+const TOLERANCE: u8 = 6;
+/// The share of pixels allowed to differ by more than `TOLERANCE`: along the border of
+/// the set, f32 and f64 can disagree about whether a point escapes at all.
+const MISMATCH_RATIO: f64 = 0.02;
+
+/// The GPU to render on, None to skip the test without one.
+fn gpu() -> Option<Offscreen> {
+    match pollster::block_on(Offscreen::new(&AdapterChoice::default())) {
+        Ok(gpu) => Some(gpu),
+        Err(Error::NoAdapter) => {
+            eprintln!("Skipping the comparison, there is no GPU");
+            None
+        }
+        Err(error) => panic!("failed to set up the GPU: {}", error),
+    }
+}
+
+fn compare(camera: Camera, iteration: Iteration) {
+    let Some(gpu) = gpu() else {
+        return;
+    };
+    let style = Style::default();
+    let gpu_pixels = gpu
+        .render(
+            camera,
+            PhysicalSize::new(RESOLUTION.0 as u32, RESOLUTION.1 as u32),
+            iteration,
+            &style,
+        )
+        .expect("failed to render on the GPU");
+    let cpu_pixels = mandelbrot::render(&RenderParams {
+        camera,
+        resolution: RESOLUTION,
+        iteration,
+        style,
+        threads: 0,
+    });
+    assert_similar(&gpu_pixels, &cpu_pixels);
+}
+
+/// Assert that at most `MISMATCH_RATIO` of the RGB pixels of `a` and `b` differ.
+fn assert_similar(a: &[u8], b: &[u8]) {
+    assert_eq!(a.len(), b.len());
+    let mismatches = a
+        .chunks_exact(3)
+        .zip(b.chunks_exact(3))
+        .filter(|(a, b)| {
+            a.iter()
+                .zip(b.iter())
+                .any(|(a, b)| a.abs_diff(*b) > TOLERANCE)
+        })
+        .count();
+    let ratio = mismatches as f64 / (a.len() / 3) as f64;
+    assert!(
+        ratio <= MISMATCH_RATIO,
+        "{} of the pixels differ, more than the {} allowed",
+        ratio,
+        MISMATCH_RATIO
+    );
+}
+
+#[test]
+fn whole_set() {
+    compare(
+        Camera {
+            center: mandelbrot::DEFAULT_CENTER_POINT,
+            zoom: mandelbrot::DEFAULT_ZOOM,
+        },
+        Iteration::default(),
+    );
+}
+
+#[test]
+fn seahorse_valley() {
+    compare(
+        Camera {
+            center: (-0.745, 0.11),
+            zoom: 50.0,
+        },
+        Iteration {
+            limit: 500,
+            ..Iteration::default()
+        },
+    );
+}
+
+#[test]
+fn julia_orbit_trap() {
+    // The corners of the view start outside the bailout radius, and escape right away.
+    // The constant is in the main cardioid, where the orbits settle the same in f32.
+    compare(
+        Camera {
+            center: (0.0, 0.0),
+            zoom: 0.5,
+        },
+        Iteration {
+            julia_c: Some(Complex::new(-0.4, 0.3)),
+            trap: Some(OrbitTrap {
+                shape: TrapShape::Point,
+                center: Complex::new(0.0, 0.0),
+                radius: 0.5,
+            }),
+            ..Iteration::default()
+        },
+    );
+}
+
+#[test]
+fn pixels_map_to_the_same_points() {
+    let resolution = PhysicalSize::new(RESOLUTION.0 as u32, RESOLUTION.1 as u32);
+    for y_axis in [YAxis::Up, YAxis::Down] {
+        let (top_left, (width, height)) =
+            mandelbrot::center_to_start_conditions((-0.745, 0.11), 50.0, resolution, y_axis);
+        let upper_left = Complex::new(top_left.0, top_left.1);
+        let lower_right = Complex::new(top_left.0 + width, top_left.1 - height);
+        let (last_column, last_row) = (RESOLUTION.0 - 1, RESOLUTION.1 - 1);
+        let pixels = [
+            (0, 0),
+            (last_column, 0),
+            (0, last_row),
+            (last_column, last_row),
+            (RESOLUTION.0 / 2, RESOLUTION.1 / 2),
+        ];
+        for pixel in pixels {
+            let cpu = mandelbrot::cpu::pixel_to_view(RESOLUTION, pixel, upper_left, lower_right);
+            // The fragment shader is invoked at the centers of the pixels
+            let position = (pixel.0 as f64 + 0.5, pixel.1 as f64 + 0.5);
+            let gpu =
+                mandelbrot::gpu::shader_point(top_left, (width, height), resolution, position);
+            let pixel_size = width / RESOLUTION.0 as f64;
+            assert!(
+                (cpu.re - gpu.0).abs() < 1.0e-6 * pixel_size
+                    && (cpu.im - gpu.1).abs() < 1.0e-6 * pixel_size,
+                "pixel {:?} is at {} on the CPU and at {:?} on the GPU",
+                pixel,
+                cpu,
+                gpu
+            );
+        }
+    }
+}
+
+#[test]
+fn tiled_render() {
+    let Some(mut gpu) = gpu() else {
+        return;
+    };
+    let camera = Camera {
+        center: (-0.745, 0.11),
+        zoom: 50.0,
+    };
+    let resolution = PhysicalSize::new(RESOLUTION.0 as u32, RESOLUTION.1 as u32);
+    let iteration = Iteration {
+        limit: 500,
+        ..Iteration::default()
+    };
+    let style = Style::default();
+    let whole = gpu
+        .render(camera, resolution, iteration, &style)
+        .expect("failed to render on the GPU");
+    // Tiles that don't divide the resolution evenly, leaving narrower ones at the edges
+    gpu.max_tile_size = 48;
+    let tiled = gpu
+        .render(camera, resolution, iteration, &style)
+        .expect("failed to render the tiles on the GPU");
+    // Every tile samples the points of the whole render, down to the last bit
+    assert!(whole == tiled, "the tiles differ from the whole render");
+}
@@ -0,0 +1,326 @@
+//! Known values of the math at the core of the CPU renderer, and the renders it has to
+//! agree with.
+use mandelbrot::cpu::{
+    escape_time, pixel_to_view, render_parallel, render_reference, ColorMapping, EscapeCounts,
+    EscapeNorm, FractalKind, InitialZ, Iteration, Parallelism,
+};
+use mandelbrot::export::{Camera, Style};
+use mandelbrot::render_config::RenderConfig;
+use mandelbrot::{view_is_valid, RenderParams};
+use num::Complex;
+use winit::dpi::PhysicalSize;
+
+#[test]
+fn points_in_the_set_never_escape() {
+    let iteration = Iteration::default();
+    // The center of the main cardioid, the period 2 bulb, and a point on the real axis
+    // outside of both that has to be iterated to the limit
+    for point in [
+        Complex::new(0.0, 0.0),
+        Complex::new(-1.0, 0.0),
+        Complex::new(-1.8, 0.0),
+    ] {
+        assert_eq!(escape_time(point, iteration), None, "{}", point);
+    }
+}
+
+#[test]
+fn points_outside_escape_at_known_counts() {
+    let iteration = Iteration::default();
+    // 2 lands right on the bailout radius after the first step, leaving nothing to smooth
+    assert_eq!(escape_time(Complex::new(2.0, 0.0), iteration), Some(2.0));
+    // Just past the cusp of the cardioid at 0.25, the orbit takes a while to leave
+    let count = escape_time(Complex::new(0.26, 0.0), iteration).unwrap();
+    assert!((30.0..31.0).contains(&count), "{}", count);
+}
+
+#[test]
+fn escape_norms_move_the_bailout() {
+    let norm = |norm| Iteration {
+        norm,
+        ..Iteration::default()
+    };
+    let l2 = norm(EscapeNorm::L2);
+    assert_eq!(escape_time(Complex::new(-0.75, 0.25), l2), Some(13.619476));
+    assert_eq!(escape_time(Complex::new(-1.75, 1.0), l2), Some(1.9839545));
+    assert_eq!(escape_time(Complex::new(0.0, 1.0), l2), None);
+
+    // The square the L∞ norm bails out of reaches past the circle, so some orbits escape
+    // a step later, while the diamond of the L1 norm fits inside it and catches them
+    // earlier
+    let count = escape_time(Complex::new(-1.75, 1.0), norm(EscapeNorm::LInfinity)).unwrap();
+    assert!((2.5..2.6).contains(&count), "{}", count);
+    let count = escape_time(Complex::new(-0.75, 0.25), norm(EscapeNorm::L1)).unwrap();
+    assert_eq!(count, 13.0);
+    // The orbit of i cycles through -1 + i, right on the edge of the diamond
+    let i = Complex::new(0.0, 1.0);
+    assert_eq!(escape_time(i, norm(EscapeNorm::LInfinity)), None);
+    assert_eq!(escape_time(i, norm(EscapeNorm::L1)), Some(3.0));
+}
+
+#[test]
+fn initial_z_warps_the_set() {
+    let start = |initial_z| Iteration {
+        initial_z,
+        ..Iteration::default()
+    };
+    // Starting from zero is the classic set, with the same counts as the default
+    let zero = start(InitialZ::Zero);
+    assert_eq!(escape_time(Complex::new(2.0, 0.0), zero), Some(2.0));
+    let count = escape_time(Complex::new(0.26, 0.0), zero).unwrap();
+    assert!((30.0..31.0).contains(&count), "{}", count);
+    assert_eq!(escape_time(Complex::new(-0.75, 0.3), zero), Some(11.450957));
+    assert_eq!(escape_time(Complex::new(0.3, 0.6), zero), Some(15.146899));
+
+    for initial_z in [InitialZ::HalfPoint, InitialZ::Ripple] {
+        for point in [Complex::new(-0.75, 0.3), Complex::new(0.3, 0.6)] {
+            assert_ne!(
+                escape_time(point, start(initial_z)),
+                escape_time(point, zero),
+                "{:?} {}",
+                initial_z,
+                point
+            );
+        }
+    }
+}
+
+#[test]
+fn interior_points_get_their_periods() {
+    let iteration = Iteration {
+        limit: 1000,
+        interior_periods: true,
+        ..Iteration::default()
+    };
+    // The centers of the main cardioid, the period 2 bulb, the period 3 bulb on the real
+    // axis and the one above the cardioid, the last two iterated to find their periods
+    for (point, period) in [
+        (Complex::new(0.0, 0.0), 1.0),
+        (Complex::new(-1.0, 0.0), 2.0),
+        (Complex::new(-1.754_877_7, 0.0), 3.0),
+        (Complex::new(-0.122_561_2, 0.744_861_8), 3.0),
+    ] {
+        assert_eq!(escape_time(point, iteration), Some(-period), "{}", point);
+    }
+    // Escaping points keep their counts
+    assert_eq!(escape_time(Complex::new(2.0, 0.0), iteration), Some(2.0));
+}
+
+#[test]
+fn periodicity_checking_keeps_the_counts() {
+    let bounds = (96, 72);
+    let (upper_left, lower_right) = (Complex::new(-2.5, 1.2), Complex::new(1.0, -1.2));
+    let iteration = Iteration {
+        limit: 1000,
+        ..Iteration::default()
+    };
+    let mut reference = EscapeCounts::default();
+    reference.resize(bounds);
+    render_reference(&mut reference, upper_left, lower_right, iteration);
+
+    let checked = Iteration {
+        periodicity_checking: true,
+        ..iteration
+    };
+    // Every point escapes at the same count as before, and the same ones don't, both
+    // pixel by pixel and in the lanes
+    let mut counts = EscapeCounts::default();
+    counts.resize(bounds);
+    render_reference(&mut counts, upper_left, lower_right, checked);
+    assert_eq!(counts.counts(), reference.counts());
+    render_parallel(
+        &mut counts,
+        upper_left,
+        lower_right,
+        checked,
+        Parallelism::default(),
+        1,
+    );
+    assert_eq!(counts.counts(), reference.counts());
+}
+
+#[test]
+fn pixels_map_to_their_centers() {
+    let (upper_left, lower_right) = (Complex::new(-3.0, 1.5), Complex::new(2.0, -3.5));
+    let bounds = (5, 5);
+    // Half a pixel into the view from its upper left corner, as the shader samples it
+    assert_eq!(
+        pixel_to_view(bounds, (0, 0), upper_left, lower_right),
+        Complex::new(-2.5, 1.0)
+    );
+    assert_eq!(
+        pixel_to_view(bounds, (2, 2), upper_left, lower_right),
+        Complex::new(-0.5, -1.0)
+    );
+}
+
+#[test]
+fn broken_views_are_invalid() {
+    let window = PhysicalSize::new(1024, 768);
+    assert!(view_is_valid((-0.5, 0.0), 1.0, window));
+    assert!(view_is_valid((-0.5, 0.0), 1.0e300, window));
+    assert!(!view_is_valid((f64::NAN, 0.0), 1.0, window));
+    assert!(!view_is_valid((-0.5, f64::INFINITY), 1.0, window));
+    assert!(!view_is_valid((-0.5, 0.0), f64::NAN, window));
+    assert!(!view_is_valid((-0.5, 0.0), 0.0, window));
+    // Deep enough for the size of the view to be a denormal
+    assert!(!view_is_valid((-0.5, 0.0), f64::MAX, window));
+}
+
+#[test]
+fn color_mappings_span_the_palette() {
+    let limit = 1000;
+    for mapping in [
+        ColorMapping::Linear,
+        ColorMapping::SquareRoot,
+        ColorMapping::Logarithmic,
+    ] {
+        assert_eq!(mapping.position(0.0, limit), 0.0, "{:?}", mapping);
+        assert!(
+            (mapping.position(limit as f32, limit) - 1.0).abs() < 1e-6,
+            "{:?}",
+            mapping
+        );
+    }
+    // Both of the others lift the low counts above where the linear mapping puts them
+    let linear = ColorMapping::Linear.position(10.0, limit);
+    let square_root = ColorMapping::SquareRoot.position(10.0, limit);
+    let logarithmic = ColorMapping::Logarithmic.position(10.0, limit);
+    assert!(linear < square_root && square_root < logarithmic);
+}
+
+#[test]
+fn stats_describe_the_counts() {
+    let mut counts = EscapeCounts::default();
+    // The whole set and some of its surroundings, at a low limit most of the boundary
+    // escapes close to
+    let iteration = Iteration {
+        limit: 20,
+        ..Iteration::default()
+    };
+    counts.resize((64, 48));
+    render_reference(
+        &mut counts,
+        Complex::new(-2.5, 1.2),
+        Complex::new(1.0, -1.2),
+        iteration,
+    );
+    let stats = counts.stats(iteration.limit);
+    let escaped: Vec<f32> = counts.counts().iter().flatten().copied().collect();
+    let interior = counts.counts().len() - escaped.len();
+    assert!(interior > 0 && !escaped.is_empty());
+    assert_eq!(
+        stats.interior,
+        interior as f32 / counts.counts().len() as f32
+    );
+    assert_eq!(
+        stats.min,
+        escaped.iter().copied().fold(f32::INFINITY, f32::min)
+    );
+    assert_eq!(stats.max, escaped.iter().copied().fold(0.0, f32::max));
+    assert!(stats.min <= stats.mean && stats.mean <= stats.max);
+    assert!((0.0..=1.0).contains(&stats.near_limit));
+
+    // Nothing escapes a render of the main cardioid alone
+    counts.resize((8, 8));
+    render_reference(
+        &mut counts,
+        Complex::new(-0.1, 0.1),
+        Complex::new(0.1, -0.1),
+        iteration,
+    );
+    let stats = counts.stats(iteration.limit);
+    assert_eq!((stats.min, stats.max, stats.mean), (0.0, 0.0, 0.0));
+    assert_eq!(stats.interior, 1.0);
+    assert!(!stats.limit_too_low());
+}
+
+#[test]
+fn parallel_renders_match_the_reference() {
+    // Sizes no number of threads divides evenly, so the last band and tile are partial
+    let bounds = (203, 151);
+    let (upper_left, lower_right) = (Complex::new(-0.8, 0.2), Complex::new(-0.7, 0.1));
+    let iteration = Iteration::default();
+    let bits = |counts: &EscapeCounts| -> Vec<Option<u32>> {
+        counts
+            .counts()
+            .iter()
+            .map(|count| count.map(f32::to_bits))
+            .collect()
+    };
+    let mut reference = EscapeCounts::default();
+    reference.resize(bounds);
+    render_reference(&mut reference, upper_left, lower_right, iteration);
+    for parallelism in Parallelism::ALL {
+        for threads in [1, 3, 8] {
+            let mut counts = EscapeCounts::default();
+            counts.resize(bounds);
+            render_parallel(
+                &mut counts,
+                upper_left,
+                lower_right,
+                iteration,
+                parallelism,
+                threads,
+            );
+            assert!(
+                bits(&counts) == bits(&reference),
+                "{:?} on {} threads",
+                parallelism,
+                threads
+            );
+        }
+    }
+}
+
+#[test]
+fn frames_hold_the_render_packed() {
+    let params = RenderParams {
+        camera: Camera {
+            center: (-0.5, 0.0),
+            zoom: 1.0,
+        },
+        resolution: (40, 30),
+        iteration: Iteration::default(),
+        style: Style::default(),
+        threads: 2,
+    };
+    let pixels = mandelbrot::render(&params);
+    let mut frame = vec![0; 40 * 30];
+    mandelbrot::render_frame(&params, &mut frame);
+    for (packed, pixel) in frame.iter().zip(pixels.chunks_exact(3)) {
+        assert_eq!(packed.to_be_bytes(), [0, pixel[0], pixel[1], pixel[2]]);
+    }
+}
+
+#[test]
+fn render_configs_survive_the_round_trip() {
+    let config = RenderConfig {
+        center_point: (-0.743643887037151, 0.13182590420533),
+        zoom: 2500.0,
+        resolution: (32, 24),
+        max_iterations: 1024,
+        fractal_kind: FractalKind::BurningShip,
+        power: 3,
+        julia_c: Some((-0.8, 0.156)),
+        color_mapping: ColorMapping::Logarithmic,
+        gamma: 0.5,
+        supersampling: 2,
+        ..RenderConfig::default()
+    };
+    let path = std::env::temp_dir().join("mandelbrot_render_config_round_trip.json");
+    config.save(&path).unwrap();
+    let loaded = RenderConfig::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, config);
+    assert_eq!(
+        mandelbrot::render(&loaded.params(2).unwrap()),
+        mandelbrot::render(&config.params(2).unwrap())
+    );
+
+    let broken = RenderConfig {
+        resolution: (0, 24),
+        ..config
+    };
+    assert!(broken.params(2).is_err());
+}